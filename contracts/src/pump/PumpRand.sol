@@ -18,7 +18,7 @@ contract PumpRand {
     // 1 eth to graduate
     uint256 public constant WEI_GRADUATION = 1e18;
 
-    uint16 feeBps;
+    uint16 public feeBps;
     uint32 public coinsCreated;
     address public deployer;
     uint256 private fees;
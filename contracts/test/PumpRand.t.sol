@@ -77,6 +77,11 @@ contract PumpRandTest is Test {
         assertLe(amountsSell[1], 100);
     }
 
+    function testFeeBps() public {
+        PumpRand feePump = new PumpRand(250, address(router));
+        assertEq(feePump.feeBps(), 250);
+    }
+
     /// Once the coin has graduated, no further buys should be allowed.
     function testNoAdditionalBuysAfterGraduation() public {
         uint32 coinId = pump.createCoin("TestCoin", "TST", 21_000_000_000_000_000_000_000);
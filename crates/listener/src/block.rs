@@ -0,0 +1,200 @@
+use alloy_primitives::Address;
+use bigdecimal::{BigDecimal, Zero};
+use diesel::{Connection, PgConnection};
+use std::collections::HashMap;
+
+use pump::{
+    client::block::Block,
+    contract::SolidityCoin,
+    db::{
+        models::{self, CandleVolume},
+        store,
+    },
+    error::PumpError,
+    CandleUpdate, ListenerUpdate, TradeUpdate,
+};
+
+/// A DB write decoded from one of a block's logs
+pub(crate) enum BlockOp {
+    CoinCreated { coin_id: i64, coin: SolidityCoin },
+    WeiInUpdated { coin_id: i64, wei_in: BigDecimal, tx: String, log_index: i64 },
+    CoinGraduated { coin_id: i64 },
+    DeployedToDex { coin_id: i64, lp_token: Address, pool: models::Pool },
+    Swap { trade: models::Trade, update: TradeUpdate },
+}
+
+/// DB writes made for a block, so they can be undone if the block is orphaned
+#[derive(Default)]
+pub(crate) struct BlockJournal {
+    /// (tx, log index) of each trade
+    pub trades: Vec<(String, i64)>,
+    /// (coin id, wei in before the update)
+    pub wei_ins: Vec<(i64, BigDecimal)>,
+    /// (tx, log index) of each purchase or refund on a bonding curve
+    pub wei_in_updates: Vec<(String, i64)>,
+    pub graduations: Vec<i64>,
+    pub deployments: Vec<i64>,
+    /// ids of the block's `pool_prices` rows
+    pub candles: Vec<i64>,
+}
+
+impl BlockJournal {
+    pub fn coin_ids(&self) -> impl Iterator<Item = i64> + '_ {
+        self.wei_ins
+            .iter()
+            .map(|(coin_id, _)| *coin_id)
+            .chain(self.graduations.iter().copied())
+            .chain(self.deployments.iter().copied())
+    }
+}
+
+/// Everything a confirmed block writes, staged so it can be applied in one transaction
+pub(crate) struct BlockUpdate {
+    pub block: Block,
+    pub ops: Vec<BlockOp>,
+    /// UI prices from each pool's Sync events, in log order
+    pub prices: HashMap<Address, Vec<BigDecimal>>,
+    /// volume from each pool's Swap events
+    pub volumes: HashMap<Address, CandleVolume>,
+}
+
+/// What's left to do once a block's transaction commits
+#[derive(Default)]
+pub(crate) struct CommittedBlock {
+    pub journal: BlockJournal,
+    pub updates: Vec<ListenerUpdate>,
+    /// graduated coins queued for the keeper to deploy to the dex
+    pub graduations: Vec<u32>,
+}
+
+impl BlockUpdate {
+    pub fn new(block: Block) -> BlockUpdate {
+        BlockUpdate { block, ops: vec![], prices: HashMap::new(), volumes: HashMap::new() }
+    }
+
+    /// Applies every write for the block, including its candles,
+    /// and advances the chain's cursor to it. Run this inside a transaction.
+    /// Each write gets its own savepoint: one that can never succeed is logged and skipped,
+    /// while connection and serialization failures abort the block so it's retried
+    pub fn apply(
+        self,
+        conn: &mut PgConnection,
+        chain_id: u64,
+        wei_graduation: &BigDecimal,
+    ) -> Result<CommittedBlock, PumpError> {
+        let number = self.block.number;
+        let mut committed = CommittedBlock::default();
+        for op in self.ops {
+            let applied = conn
+                .transaction(|conn| apply_op(conn, self.block, op, wei_graduation, &mut committed));
+            skip_permanent(number, applied)?;
+        }
+
+        // every Uniswap V2 swap also emits a Sync, so each pool with volume has prices
+        let mut volumes = self.volumes;
+        for (lp_token, mut prices) in self.prices {
+            let volume = volumes.remove(&lp_token).unwrap_or_default();
+            let applied = conn.transaction(|conn| {
+                if let Some(open) = store::get_last_closing_price(conn, lp_token)? {
+                    prices.insert(0, open);
+                }
+                let price = models::NewPoolPrice::try_new(&lp_token, self.block, &prices, volume)?;
+                let id = store::add_price(conn, price.clone())?;
+                committed.updates.push(ListenerUpdate::Candle(CandleUpdate {
+                    pool: lp_token.to_string(),
+                    block_number: number,
+                    candle: price.into(),
+                }));
+                committed.journal.candles.push(id);
+                Ok(())
+            });
+            skip_permanent(number, applied)?;
+        }
+
+        store::set_block_cursor(conn, chain_id, number)?;
+        Ok(committed)
+    }
+}
+
+/// Writes one op, recording it only once every write has succeeded,
+/// so a rolled back op leaves nothing behind in `committed`
+fn apply_op(
+    conn: &mut PgConnection,
+    block: Block,
+    op: BlockOp,
+    wei_graduation: &BigDecimal,
+    committed: &mut CommittedBlock,
+) -> Result<(), PumpError> {
+    match op {
+        BlockOp::CoinCreated { coin_id, coin } => {
+            let coin = store::upsert_verified(conn, coin_id, coin)?;
+            committed.updates.push(ListenerUpdate::VerifiedCoin(coin));
+        }
+        BlockOp::WeiInUpdated { coin_id, wei_in, tx, log_index } => {
+            let previous = store::get_coin(conn, coin_id)?.wei_in;
+            store::add_wei_in_update(
+                conn,
+                &models::NewWeiInUpdate {
+                    coin_id,
+                    block_number: block.number as i64,
+                    log_index,
+                    tx: tx.clone(),
+                    time: block.timestamp,
+                    wei_in: wei_in.clone(),
+                    delta: &wei_in - &previous,
+                },
+            )?;
+            store::update_wei_in(conn, coin_id, wei_in.clone())?;
+            committed.journal.wei_in_updates.push((tx, log_index));
+            committed.journal.wei_ins.push((coin_id, previous));
+            if wei_in == BigDecimal::zero() {
+                // don't stream this update because they'll get a created message
+                return Ok(());
+            }
+            if &wei_in >= wei_graduation {
+                // don't stream this update because they'll get a graduated message
+                return Ok(());
+            }
+            committed.updates.push(ListenerUpdate::WeiInUpdated { id: coin_id, wei_in });
+        }
+        BlockOp::CoinGraduated { coin_id } => {
+            store::graduate_coin(conn, coin_id)?;
+            // skip deploying if we're replaying a graduation that was already deployed
+            let deploy = store::get_coin(conn, coin_id)?.deployed_pool.is_none();
+            if deploy {
+                store::enqueue_deployment(conn, coin_id)?;
+                committed.graduations.push(coin_id as u32);
+            }
+            committed.journal.graduations.push(coin_id);
+            committed.updates.push(ListenerUpdate::GraduatedCoin { id: coin_id });
+        }
+        BlockOp::DeployedToDex { coin_id, lp_token, pool } => {
+            store::upsert_deployed_pool(conn, pool)?;
+            store::update_deployed_pool(conn, coin_id, lp_token)?;
+            committed.journal.deployments.push(coin_id);
+            committed.updates.push(ListenerUpdate::DeployedToDex {
+                id: coin_id,
+                deployed_pool: lp_token.to_string(),
+            });
+        }
+        BlockOp::Swap { trade, mut update } => {
+            store::add_trade(conn, &trade)?;
+            update.coin_id = store::get_coin_id_by_pool(conn, &trade.pool)?;
+            committed.journal.trades.push((trade.tx, trade.log_index));
+            committed.updates.push(ListenerUpdate::Trade(update));
+        }
+    }
+    Ok(())
+}
+
+/// Passes through errors worth retrying the block for, and logs the rest
+fn skip_permanent(number: u64, applied: Result<(), PumpError>) -> Result<(), PumpError> {
+    match applied {
+        Err(e) if e.is_transient() => Err(e),
+        Err(e) => {
+            log::error!("Skipping write in block {}: {:?}", number, e);
+            Ok(())
+        }
+        Ok(()) => Ok(()),
+    }
+}
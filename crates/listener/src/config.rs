@@ -0,0 +1,106 @@
+use alloy_rpc_types_eth::BlockNumberOrTag;
+use std::time::Duration;
+
+const ANVIL_CHAIN_ID: u64 = 31337;
+const DEVNET_CHAIN_ID: u64 = 5124;
+const DEFAULT_BACKFILL_CHUNK_SIZE: u64 = 1_000;
+const DEFAULT_KEEPER_POLL_MS: u64 = 5_000;
+const DEFAULT_KEEPER_MAX_ATTEMPTS: u64 = 8;
+const DEFAULT_KEEPER_STUCK_SECS: u64 = 120;
+
+/// Which block the node must report before we write a block to the DB
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Finality {
+    /// only wait for the confirmation depth
+    Latest,
+    Safe,
+    Finalized,
+}
+
+impl Finality {
+    fn parse(value: &str) -> Option<Finality> {
+        match value.to_lowercase().as_str() {
+            "latest" => Some(Finality::Latest),
+            "safe" => Some(Finality::Safe),
+            "finalized" => Some(Finality::Finalized),
+            _ => None,
+        }
+    }
+
+    pub fn tag(&self) -> Option<BlockNumberOrTag> {
+        match self {
+            Finality::Latest => None,
+            Finality::Safe => Some(BlockNumberOrTag::Safe),
+            Finality::Finalized => Some(BlockNumberOrTag::Finalized),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct ListenerConfig {
+    /// how many blocks behind the head a block must be before we write it
+    pub confirmations: u64,
+    pub finality: Finality,
+    pub backfill_chunk_size: u64,
+}
+
+fn env_u64(key: &str) -> Option<u64> {
+    std::env::var(key).ok().and_then(|value| value.parse::<u64>().ok())
+}
+
+fn default_confirmations(chain_id: u64) -> u64 {
+    match chain_id {
+        // anvil mines on demand and never reorgs
+        ANVIL_CHAIN_ID => 1,
+        DEVNET_CHAIN_ID => 2,
+        _ => 3,
+    }
+}
+
+impl ListenerConfig {
+    /// Reads `CONFIRMATIONS_<chain id>` (or `CONFIRMATIONS`), `FINALITY` and `BACKFILL_CHUNK_SIZE`
+    pub fn from_env(chain_id: u64) -> ListenerConfig {
+        let confirmations = env_u64(&format!("CONFIRMATIONS_{}", chain_id))
+            .or_else(|| env_u64("CONFIRMATIONS"))
+            .unwrap_or_else(|| default_confirmations(chain_id));
+        let finality = match std::env::var("FINALITY") {
+            Ok(value) => Finality::parse(&value)
+                .unwrap_or_else(|| panic!("FINALITY must be latest, safe or finalized: {}", value)),
+            Err(_) => Finality::Latest,
+        };
+        let backfill_chunk_size =
+            env_u64("BACKFILL_CHUNK_SIZE").unwrap_or(DEFAULT_BACKFILL_CHUNK_SIZE);
+
+        ListenerConfig {
+            // logs for the head block can arrive after its header, so never write it right away
+            confirmations: confirmations.max(1),
+            finality,
+            backfill_chunk_size: backfill_chunk_size.max(1),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct KeeperConfig {
+    /// how often to check on deployments, and the base of the retry backoff
+    pub poll_interval: Duration,
+    /// give up on a coin after this many deployGraduated txs
+    pub max_attempts: i32,
+    /// replace a tx that hasn't been mined after this long
+    pub stuck_after: Duration,
+}
+
+impl KeeperConfig {
+    /// Reads `KEEPER_POLL_MS`, `KEEPER_MAX_ATTEMPTS` and `KEEPER_STUCK_SECS`
+    pub fn from_env() -> KeeperConfig {
+        let poll_ms = env_u64("KEEPER_POLL_MS").unwrap_or(DEFAULT_KEEPER_POLL_MS);
+        let max_attempts = env_u64("KEEPER_MAX_ATTEMPTS").unwrap_or(DEFAULT_KEEPER_MAX_ATTEMPTS);
+        let stuck_secs = env_u64("KEEPER_STUCK_SECS").unwrap_or(DEFAULT_KEEPER_STUCK_SECS);
+
+        KeeperConfig {
+            poll_interval: Duration::from_millis(poll_ms.max(100)),
+            max_attempts: max_attempts.clamp(1, i32::MAX as u64) as i32,
+            stuck_after: Duration::from_secs(stuck_secs),
+        }
+    }
+}
@@ -1,10 +1,16 @@
-use alloy_primitives::{hex, Address, FixedBytes};
+use alloy_primitives::{hex, Address, B256};
 use alloy_pubsub::SubscriptionStream;
 use alloy_rpc_types_eth::{Header, Log};
 use alloy_sol_types::SolEvent;
-use bigdecimal::{BigDecimal, Zero};
+use bigdecimal::BigDecimal;
 use chrono::DateTime;
-use std::{collections::HashMap, num::NonZero};
+use diesel::Connection;
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    num::NonZero,
+    sync::Arc,
+};
+use tokio::sync::Notify;
 
 use pump::{
     client::{
@@ -14,17 +20,22 @@ use pump::{
     },
     contract::{pair::UniswapV2Pair, pump::PumpRand},
     db::{
-        models::{self, Trade},
+        models::{self, CandleVolume, Trade},
         pool::{connect, PgConn, PgPool},
         store,
     },
     error::PumpError,
+    CandleUpdate, ListenerMessage, ListenerUpdate, TradeUpdate, UpdateTransport,
 };
 
-use crate::sock::SockWriter;
+use crate::{
+    block::{BlockJournal, BlockOp, BlockUpdate},
+    config::ListenerConfig,
+    publish::{notify, Publisher},
+};
 
-const CONFIRMATIONS: u64 = 1;
-const WEI_IN_GRADUATION: u64 = 10u64.pow(18);
+/// how many recent blocks we can roll back after a reorg
+const REORG_WINDOW: u64 = 64;
 
 pub fn fmt_hex<T: AsRef<[u8]>>(value: T) -> String {
     let bytes = value.as_ref();
@@ -44,90 +55,237 @@ fn fmt_px(px: BigDecimal) -> String {
         .to_engineering_notation()
 }
 
-enum BlockKind {
-    Block(Block),
-    NumberOnly(u64),
-}
-
-impl BlockKind {
-    fn number(&self) -> u64 {
-        match self {
-            BlockKind::Block(block) => block.number,
-            BlockKind::NumberOnly(number) => *number,
-        }
-    }
-}
-
-#[derive(Default)]
-struct PendingLogs {
-    wei_in_updates: Vec<Log<PumpRand::WeiInUpdated>>,
-    deployed_to_dexs: Vec<Log<PumpRand::DeployedToDex>>,
-    swaps: Vec<Log<UniswapV2Pair::Swap>>,
-    syncs: Vec<Log<UniswapV2Pair::Sync>>,
-}
-
 pub struct LogHandler {
     pool: PgPool,
     client: PumpClient,
-    sock_writer: Option<SockWriter>,
+    publisher: Publisher,
     ws: PumpWsClient,
-    prices: HashMap<u64, (Block, HashMap<Address, Vec<BigDecimal>>)>,
     pools: HashMap<Address, Pool>,
     block_timestamps: HashMap<u64, i64>,
-    pending_logs: HashMap<u64, PendingLogs>,
+    /// logs from blocks that haven't been confirmed yet, written once they are
+    pending_logs: BTreeMap<u64, Vec<Log>>,
+    /// logs at or below this height were already fetched by backfill
+    synced_to: u64,
+    /// every block at or below this height is written to the DB
+    committed_to: u64,
+    config: ListenerConfig,
+    /// sequence number of the next message we publish
+    next_seq: u64,
+    block_hashes: BTreeMap<u64, B256>,
+    /// writes for the blocks committed within `REORG_WINDOW`. Only kept in memory,
+    /// so blocks committed before a restart can't be rolled back
+    journals: BTreeMap<u64, BlockJournal>,
+    /// wakes the keeper when coins graduate
+    keeper: Arc<Notify>,
 }
 
 impl LogHandler {
-    pub async fn new(pool: PgPool, client: PumpClient) -> Result<LogHandler, PumpError> {
+    pub async fn new(
+        pool: PgPool,
+        client: PumpClient,
+        keeper: Arc<Notify>,
+    ) -> Result<LogHandler, PumpError> {
         let ws = PumpWsClient::new(client.chain_id).await?;
         let mut conn = connect(&pool)?;
+        let config = ListenerConfig::from_env(client.chain_id);
+        log::info!(
+            "Writing blocks {} confirmations deep with {:?} finality",
+            config.confirmations,
+            config.finality
+        );
+        let publisher = Publisher::new(UpdateTransport::from_env(), pool.clone());
 
         Ok(LogHandler {
             pool,
             client,
-            // lazily load this with self.sock()
-            sock_writer: None,
+            publisher,
             ws,
-            prices: HashMap::new(),
             pools: store::load_pools(&mut conn)?,
             block_timestamps: HashMap::new(),
-            pending_logs: HashMap::new(),
+            pending_logs: BTreeMap::new(),
+            synced_to: 0,
+            committed_to: 0,
+            config,
+            next_seq: chrono::Utc::now().timestamp_micros() as u64,
+            block_hashes: BTreeMap::new(),
+            journals: BTreeMap::new(),
+            keeper,
         })
     }
 
-    pub fn sock(&mut self) -> Option<&mut SockWriter> {
-        if self.sock_writer.is_none() {
-            self.sock_writer = match SockWriter::try_new() {
-                Ok(sock_writer) => Some(sock_writer),
-                Err(e) => {
-                    log::error!("Error creating sock writer: {:?}", e);
-                    None
+    pub async fn handle_log(&mut self, log: Log) -> Result<(), PumpError> {
+        if log.removed {
+            // the header that orphaned this log will trigger a rollback
+            log::warn!(
+                "Ignoring removed log from block {:?}, tx={:?}",
+                log.block_number,
+                log.transaction_hash
+            );
+            return Ok(());
+        }
+        if let Some(number) = log.block_number {
+            if number <= self.synced_to.max(self.committed_to) {
+                log::debug!("Skipping log from block {} already fetched by backfill", number);
+                return Ok(());
+            }
+        }
+        self.buffer_log(log.clone()).await?;
+        self.publish_provisional(&log)
+    }
+
+    /// Streams what a pump log will write before its block is confirmed.
+    /// Clients get the final update once the block is committed
+    fn publish_provisional(&mut self, log: &Log) -> Result<(), PumpError> {
+        let update = match log.topic0() {
+            Some(&PumpRand::WeiInUpdated::SIGNATURE_HASH) => {
+                let data = log.log_decode::<PumpRand::WeiInUpdated>()?.inner.data;
+                let wei_in = int_to_decimal(data.totalWeiIn);
+                if wei_in == BigDecimal::from(0) || wei_in >= self.client.config.wei_graduation {
+                    return Ok(());
+                }
+                ListenerUpdate::WeiInUpdated { id: data.coinId as i64, wei_in }
+            }
+            Some(&PumpRand::CoinGraduated::SIGNATURE_HASH) => {
+                let data = log.log_decode::<PumpRand::CoinGraduated>()?.inner.data;
+                ListenerUpdate::GraduatedCoin { id: data.coinId as i64 }
+            }
+            Some(&PumpRand::DeployedToDex::SIGNATURE_HASH) => {
+                let data = log.log_decode::<PumpRand::DeployedToDex>()?.inner.data;
+                ListenerUpdate::DeployedToDex {
+                    id: data.coinId as i64,
+                    deployed_pool: data.lpToken.to_string(),
                 }
+            }
+            Some(&UniswapV2Pair::Sync::SIGNATURE_HASH) => match self.provisional_candle(log)? {
+                Some(update) => update,
+                None => return Ok(()),
+            },
+            _ => return Ok(()),
+        };
+        self.publish(update, true);
+        Ok(())
+    }
+
+    /// The pool's candle for the block so far, built from the logs buffered for it.
+    /// Opens at the last committed close, so it can be off if the previous block isn't committed yet
+    fn provisional_candle(&self, log: &Log) -> Result<Option<ListenerUpdate>, PumpError> {
+        let number = log.block_number.ok_or_else(PumpError::no_block_number)?;
+        let lp_token = log.address();
+        let (pool, timestamp) =
+            match (self.pools.get(&lp_token), self.block_timestamps.get(&number)) {
+                (Some(pool), Some(timestamp)) => (*pool, *timestamp),
+                // we'll still send the confirmed candle
+                _ => return Ok(None),
             };
+        let weth = self.client.weth();
+
+        let mut block_logs: Vec<&Log> = match self.pending_logs.get(&number) {
+            Some(logs) => logs.iter().filter(|log| log.address() == lp_token).collect(),
+            None => return Ok(None),
+        };
+        block_logs.sort_by_key(|log| log.log_index);
+
+        let mut conn = self.conn()?;
+        let mut prices: Vec<BigDecimal> =
+            store::get_last_closing_price(&mut conn, lp_token)?.into_iter().collect();
+        let mut volume = CandleVolume::default();
+        for log in block_logs {
+            match log.topic0() {
+                Some(&UniswapV2Pair::Sync::SIGNATURE_HASH) => {
+                    let data = log.log_decode::<UniswapV2Pair::Sync>()?.inner.data;
+                    prices.push(pool.to_ui_price(weth, Pool::sync_price(&data)));
+                }
+                Some(&UniswapV2Pair::Swap::SIGNATURE_HASH) => {
+                    let data = log.log_decode::<UniswapV2Pair::Swap>()?.inner.data;
+                    if let Some((_, amount_0, amount_1, _)) = Pool::swap_price(&data) {
+                        let (base, quote) = pool.to_ui_volume(weth, &amount_0, &amount_1);
+                        volume.add_trade(base, quote);
+                    }
+                }
+                _ => {}
+            }
         }
-        return self.sock_writer.as_mut();
+
+        let block = Block { number, timestamp };
+        let price = models::NewPoolPrice::try_new(&lp_token, block, &prices, volume)?;
+        Ok(Some(ListenerUpdate::Candle(CandleUpdate {
+            pool: lp_token.to_string(),
+            block_number: number,
+            candle: price.into(),
+        })))
     }
 
-    /// returns true if we should restart the stream (for a new LP token)
-    pub async fn handle_log(&mut self, log: Log) -> Result<bool, PumpError> {
+    /// Holds onto the log until its block is confirmed
+    async fn buffer_log(&mut self, log: Log) -> Result<(), PumpError> {
+        let number = log.block_number.ok_or_else(PumpError::no_block_number)?;
+        if number <= self.committed_to {
+            // writing it again would duplicate its block's candle
+            log::debug!("Dropping log from block {} that was already committed", number);
+            return Ok(());
+        }
+        match log.topic0() {
+            Some(&PumpRand::DeployedToDex::SIGNATURE_HASH) => {
+                // start tracking the pool right away, even though we write it later,
+                // so we keep the pair's later logs
+                let lp_token = log.log_decode::<PumpRand::DeployedToDex>()?.data().lpToken;
+                self.get_pool(lp_token).await?;
+                // adding liquidity Syncs the pair before this log, so the pair subscription
+                // may have delivered it before we tracked the pool
+                for pair_log in self.client.get_pair_logs(number, number).await? {
+                    if pair_log.address() == lp_token {
+                        self.add_pending_log(number, pair_log);
+                    }
+                }
+            }
+            Some(&UniswapV2Pair::Swap::SIGNATURE_HASH)
+            | Some(&UniswapV2Pair::Sync::SIGNATURE_HASH)
+                if !self.pools.contains_key(&log.address()) =>
+            {
+                // we subscribe to pair events by topic, so skip pools that didn't come from us
+                return Ok(());
+            }
+            _ => {}
+        }
+        self.add_pending_log(number, log);
+        Ok(())
+    }
+
+    fn add_pending_log(&mut self, number: u64, log: Log) {
+        if let Some(timestamp) = log.block_timestamp {
+            // these rarely have block timestamps in reth devnet node,
+            // but they do exist ~all the time for anvil
+            self.block_timestamps.insert(number, timestamp as i64);
+        }
+        let pending = self.pending_logs.entry(number).or_default();
+        // backfill and the pair subscription can replay logs we already have
+        let duplicate = pending.iter().any(|buffered| {
+            buffered.transaction_hash == log.transaction_hash && buffered.log_index == log.log_index
+        });
+        if !duplicate {
+            pending.push(log);
+        }
+    }
+
+    /// Decodes the log into the block's staged writes
+    async fn prepare_log(&mut self, log: Log, update: &mut BlockUpdate) -> Result<(), PumpError> {
         match log.topic0() {
             Some(&PumpRand::CoinCreated::SIGNATURE_HASH) => {
-                self.handle_creation(log.log_decode::<PumpRand::CoinCreated>()?).await
+                self.handle_creation(log.log_decode::<PumpRand::CoinCreated>()?, update).await
             }
             Some(&PumpRand::WeiInUpdated::SIGNATURE_HASH) => {
-                self.handle_wei_in_updated(log.log_decode::<PumpRand::WeiInUpdated>()?).await
+                self.handle_wei_in_updated(log.log_decode::<PumpRand::WeiInUpdated>()?, update)
             }
             Some(&PumpRand::CoinGraduated::SIGNATURE_HASH) => {
-                self.handle_graduation(log.log_decode::<PumpRand::CoinGraduated>()?).await
+                self.handle_graduation(log.log_decode::<PumpRand::CoinGraduated>()?, update)
             }
             Some(&PumpRand::DeployedToDex::SIGNATURE_HASH) => {
-                self.handle_deploy(log.log_decode::<PumpRand::DeployedToDex>()?).await
+                self.handle_deploy(log.log_decode::<PumpRand::DeployedToDex>()?, update).await
             }
             Some(&UniswapV2Pair::Swap::SIGNATURE_HASH) => {
-                self.handle_swap(log.log_decode::<UniswapV2Pair::Swap>()?).await
+                self.handle_swap(log.log_decode::<UniswapV2Pair::Swap>()?, update).await
             }
             Some(&UniswapV2Pair::Sync::SIGNATURE_HASH) => {
-                self.handle_sync(log.log_decode::<UniswapV2Pair::Sync>()?).await
+                self.handle_sync(log.log_decode::<UniswapV2Pair::Sync>()?, update).await
             }
             topic => Err(PumpError::unknown_topic(topic.cloned())),
         }
@@ -136,8 +294,8 @@ impl LogHandler {
     async fn handle_creation(
         &mut self,
         log: Log<PumpRand::CoinCreated>,
-    ) -> Result<bool, PumpError> {
-        let mut conn = self.conn()?;
+        update: &mut BlockUpdate,
+    ) -> Result<(), PumpError> {
         let coin_id = log.data().coinId;
         let sol_coin = self.client.get_coin(coin_id).await?;
         log::info!(
@@ -146,87 +304,53 @@ impl LogHandler {
             fmt_hex(sol_coin.contractAddress),
             fmt_hex(sol_coin.creator)
         );
-        let coin = store::upsert_verified(&mut conn, coin_id as i64, sol_coin)?;
-        if let Some(sock) = self.sock() {
-            sock.write_verified_coin(coin)?;
-        };
-        Ok(false)
+        update.ops.push(BlockOp::CoinCreated { coin_id: coin_id as i64, coin: sol_coin });
+        Ok(())
     }
 
-    async fn handle_wei_in_updated(
+    fn handle_wei_in_updated(
         &mut self,
         log: Log<PumpRand::WeiInUpdated>,
-    ) -> Result<bool, PumpError> {
-        let block = match self.try_block(&log)? {
-            BlockKind::Block(block) => block,
-            BlockKind::NumberOnly(number) => {
-                self.pending_logs
-                    .entry(number)
-                    .or_insert_with(PendingLogs::default)
-                    .wei_in_updates
-                    .push(log);
-                return Ok(false);
-            }
-        };
+        update: &mut BlockUpdate,
+    ) -> Result<(), PumpError> {
         let data = log.data();
         log::info!(
-            "Coin[{}] purchased in block {}. Total purchased: {}",
+            "Coin[{}] wei in updated in block {}. Total purchased: {}",
             data.coinId,
-            block.number,
+            update.block.number,
             data.totalWeiIn
         );
-        let wei_in = int_to_decimal(data.totalWeiIn);
-        let mut conn = self.conn()?;
-        store::update_wei_in(&mut conn, data.coinId as i64, wei_in.clone())?;
-
-        if wei_in == BigDecimal::zero() {
-            // don't stream this update because they'll get a created message shortly
-            return Ok(false);
-        }
-        if wei_in >= BigDecimal::from(WEI_IN_GRADUATION) {
-            // don't stream this update because they'll get a graduated message shortly
-            return Ok(false);
-        }
-        if let Some(sock) = self.sock() {
-            sock.write_wei_in_updated(data.coinId as i64, wei_in)?;
+        let tx = match log.transaction_hash {
+            Some(tx) => tx.to_string(),
+            None => return Err(PumpError::missing_tx()),
         };
-        Ok(false)
+        let wei_in = int_to_decimal(data.totalWeiIn);
+        update.ops.push(BlockOp::WeiInUpdated {
+            coin_id: data.coinId as i64,
+            wei_in,
+            tx,
+            log_index: log.log_index.ok_or_else(PumpError::missing_log_index)? as i64,
+        });
+        Ok(())
     }
 
-    async fn handle_graduation(
+    fn handle_graduation(
         &mut self,
         log: Log<PumpRand::CoinGraduated>,
-    ) -> Result<bool, PumpError> {
-        let block_number = self.try_block(&log)?.number();
+        update: &mut BlockUpdate,
+    ) -> Result<(), PumpError> {
         let coin_id = log.data().coinId;
-        let mut conn = self.conn()?;
-        log::info!("Coin[{}] graduated in block {}", coin_id, block_number);
-        store::graduate_coin(&mut conn, coin_id as i64)?;
-        let tx = self.client.deploy_graduated(coin_id).await?;
-        log::info!("Called graduate({}), tx={}", coin_id, fmt_hex(tx));
-        if let Some(sock) = self.sock() {
-            sock.write_graduated_coin(coin_id as i64)?;
-        };
-        Ok(false)
+        log::info!("Coin[{}] graduated in block {}", coin_id, update.block.number);
+        update.ops.push(BlockOp::CoinGraduated { coin_id: coin_id as i64 });
+        Ok(())
     }
 
     async fn handle_deploy(
         &mut self,
         log: Log<PumpRand::DeployedToDex>,
-    ) -> Result<bool, PumpError> {
-        let block = match self.try_block(&log)? {
-            BlockKind::Block(block) => block,
-            BlockKind::NumberOnly(number) => {
-                self.pending_logs
-                    .entry(number)
-                    .or_insert_with(PendingLogs::default)
-                    .deployed_to_dexs
-                    .push(log);
-                return Ok(false);
-            }
-        };
+        update: &mut BlockUpdate,
+    ) -> Result<(), PumpError> {
         let data = log.data();
-        let mut conn = self.conn()?;
         let pool = self.get_pool(data.lpToken).await?;
         let coin_id = data.coinId as i64;
         log::info!(
@@ -234,9 +358,9 @@ impl LogHandler {
             coin_id,
             fmt_hex(data.lpToken)
         );
-        self.pools.insert(pool.lp_token, pool);
-        let created_at = DateTime::from_timestamp(block.timestamp, 0)
-            .expect(&format!("Invalid block timestamp: {}", block.timestamp))
+        let timestamp = update.block.timestamp;
+        let created_at = DateTime::from_timestamp(timestamp, 0)
+            .expect(&format!("Invalid block timestamp: {}", timestamp))
             .naive_utc();
         let pool = models::Pool {
             address: pool.lp_token.to_string(),
@@ -245,35 +369,11 @@ impl LogHandler {
             token_0: pool.token_0.to_string(),
             token_1: pool.token_1.to_string(),
             created_at,
+            decimals_0: pool.decimals_0 as i32,
+            decimals_1: pool.decimals_1 as i32,
         };
-        store::upsert_deployed_pool(&mut conn, pool)?;
-        store::update_deployed_pool(&mut conn, coin_id, data.lpToken)?;
-        if let Some(sock) = self.sock() {
-            sock.write_deployed_to_dex(coin_id, data.lpToken)?;
-        };
-        Ok(true)
-    }
-
-    fn try_block<T>(&self, log: &Log<T>) -> Result<BlockKind, PumpError> {
-        match log.block_number {
-            Some(number) => match log.block_timestamp {
-                Some(timestamp) => {
-                    // these rarely have block timestamps in reth devnet node,
-                    // but they do exist ~all the time for anvil
-                    Ok(BlockKind::Block(Block { number, timestamp: timestamp as i64 }))
-                }
-                None => {
-                    let timestamp = self.block_timestamps.get(&number);
-                    match timestamp {
-                        Some(timestamp) => {
-                            Ok(BlockKind::Block(Block { number, timestamp: *timestamp as i64 }))
-                        }
-                        None => Ok(BlockKind::NumberOnly(number)),
-                    }
-                }
-            },
-            _ => Err(PumpError::no_block_number()),
-        }
+        update.ops.push(BlockOp::DeployedToDex { coin_id, lp_token: data.lpToken, pool });
+        Ok(())
     }
 
     async fn get_pool(&mut self, lp_token: Address) -> Result<Pool, PumpError> {
@@ -287,36 +387,11 @@ impl LogHandler {
         }
     }
 
-    fn insert_price(
+    async fn handle_swap(
         &mut self,
-        pool: &Pool,
-        dex_price: BigDecimal,
-        block: Block,
-    ) -> Result<BigDecimal, PumpError> {
-        let price = pool.to_ui_price(self.client.weth(), dex_price);
-        self.prices
-            .entry(block.number)
-            .or_insert((block, HashMap::new()))
-            .1
-            .entry(pool.lp_token)
-            .or_insert(vec![])
-            .push(price.clone());
-        Ok(price)
-    }
-
-    async fn handle_swap(&mut self, log: Log<UniswapV2Pair::Swap>) -> Result<bool, PumpError> {
-        let block = match self.try_block(&log)? {
-            BlockKind::Block(block) => block,
-            BlockKind::NumberOnly(number) => {
-                self.pending_logs
-                    .entry(number)
-                    .or_insert_with(PendingLogs::default)
-                    .swaps
-                    .push(log);
-                return Ok(false);
-            }
-        };
-
+        log: Log<UniswapV2Pair::Swap>,
+        update: &mut BlockUpdate,
+    ) -> Result<(), PumpError> {
         let lp_token = log.address();
         let pool = self.get_pool(lp_token).await?;
         let data = log.data();
@@ -330,7 +405,14 @@ impl LogHandler {
             true => ("BUYS", "FOR"),
             false => ("SELLS", "AT"),
         };
-        let fmt_px = fmt_px(ui_price);
+        let (volume_base, volume_quote) =
+            pool.to_ui_volume(self.client.weth(), &amount_0, &amount_1);
+        update
+            .volumes
+            .entry(lp_token)
+            .or_default()
+            .add_trade(volume_base.clone(), volume_quote.clone());
+        let fmt_px = fmt_px(ui_price.clone());
         log::info!(
             "Trader {} {} {} {} {} in block {}",
             fmt_hex(data.to),
@@ -338,123 +420,389 @@ impl LogHandler {
             fmt_hex(token),
             fa,
             fmt_px,
-            block.number
+            update.block.number
         );
 
         let tx = match log.transaction_hash {
             Some(tx) => tx.to_string(),
             None => return Err(PumpError::missing_tx()),
         };
+        let trade_update = TradeUpdate {
+            pool: pool.lp_token.to_string(),
+            // filled in when the block is written, in case the pool was deployed in this block
+            coin_id: None,
+            side: pool.trade_side(self.client.weth(), buy_0),
+            amount_token: volume_base,
+            amount_eth: volume_quote,
+            price: ui_price,
+            trader: data.to.to_string(),
+            tx: tx.clone(),
+            time: update.block.timestamp,
+        };
         let trade = Trade {
             tx,
             pool: pool.lp_token.to_string(),
             buy_0,
             amount_0,
             amount_1,
-            time: block.timestamp,
+            time: update.block.timestamp,
             trader: data.to.to_string(),
+            log_index: log.log_index.ok_or_else(PumpError::missing_log_index)? as i64,
+            block_number: Some(update.block.number as i64),
         };
-        let mut conn = self.conn()?;
-        store::add_trade(&mut conn, &trade)?;
-        Ok(false)
-    }
-
-    async fn handle_sync(&mut self, log: Log<UniswapV2Pair::Sync>) -> Result<bool, PumpError> {
-        let block = match self.try_block(&log)? {
-            BlockKind::Block(block) => block,
-            BlockKind::NumberOnly(number) => {
-                self.pending_logs
-                    .entry(number)
-                    .or_insert_with(PendingLogs::default)
-                    .syncs
-                    .push(log);
-                return Ok(false);
-            }
-        };
+        update.ops.push(BlockOp::Swap { trade, update: trade_update });
+        Ok(())
+    }
+
+    async fn handle_sync(
+        &mut self,
+        log: Log<UniswapV2Pair::Sync>,
+        update: &mut BlockUpdate,
+    ) -> Result<(), PumpError> {
         let lp_token = log.address();
         let pool = self.get_pool(lp_token).await?;
         let data = log.data();
         let dex_price = Pool::sync_price(data);
-        let ui_price = self.insert_price(&pool, dex_price, block)?;
+        let ui_price = pool.to_ui_price(self.client.weth(), dex_price);
+        update.prices.entry(lp_token).or_default().push(ui_price.clone());
         log::info!(
             "Pool {} price is {} at top of block {}",
             fmt_hex(lp_token),
             fmt_px(ui_price),
-            block.number
+            update.block.number
         );
-        Ok(false)
+        Ok(())
     }
 
-    pub async fn flush_candle(
-        &mut self,
-        lp_token: Address,
-        mut prices: Vec<BigDecimal>,
-        block: Block,
-    ) -> Result<(), PumpError> {
+    async fn get_block(&mut self, number: u64) -> Result<Block, PumpError> {
+        match self.block_timestamps.get(&number) {
+            Some(timestamp) => Ok(Block { number, timestamp: *timestamp }),
+            None => {
+                // if we have logs in the confirmed block, but no timestamp,
+                // then request the timestamp from the RPC
+                let header = self.client.get_block_header(number).await?;
+                self.block_timestamps.insert(number, header.timestamp as i64);
+                Ok(Block { number, timestamp: header.timestamp as i64 })
+            }
+        }
+    }
+
+    /// Writes everything from a confirmed block in a single transaction
+    /// that also advances the cursor, so a restart resumes right after it
+    async fn commit_block(&mut self, number: u64, mut logs: Vec<Log>) -> Result<(), PumpError> {
+        let block = self.get_block(number).await?;
+        logs.sort_by_key(|log| log.log_index);
+        let mut update = BlockUpdate::new(block);
+        for log in logs {
+            match self.prepare_log(log, &mut update).await {
+                Ok(()) => {}
+                // these will never succeed, so don't hold up the block
+                Err(PumpError::Listener(e)) => {
+                    log::error!("Skipping log in block {}: {:?}", number, e);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        let chain_id = self.client.chain_id;
+        let wei_graduation = &self.client.config.wei_graduation;
+        let in_transaction = self.publisher.in_transaction();
+        let mut seq = self.next_seq;
         let mut conn = self.conn()?;
-        if let Some(open) = store::get_last_closing_price(&mut conn, lp_token)? {
-            prices.insert(0, open);
+        let committed = conn.transaction(|conn| {
+            let committed = update.apply(conn, chain_id, wei_graduation)?;
+            if in_transaction {
+                // clients only hear about the block if its writes commit
+                for update in committed.updates.iter() {
+                    let message =
+                        ListenerMessage { seq, update: update.clone(), provisional: false };
+                    notify(conn, &message)?;
+                    seq += 1;
+                }
+            }
+            Ok::<_, PumpError>(committed)
+        })?;
+        self.next_seq = seq;
+        self.journals.insert(number, committed.journal);
+        self.committed_to = self.committed_to.max(number);
+
+        if !in_transaction {
+            for update in committed.updates {
+                self.publish(update, false);
+            }
+        }
+        if !committed.graduations.is_empty() {
+            log::info!("Queued coins {:?} for deployment", committed.graduations);
+            self.keeper.notify_one();
         }
-        let price = models::NewPoolPrice::try_new(&lp_token, block, &prices)?;
-        store::add_price(&mut conn, price)?;
         Ok(())
     }
 
-    async fn flush_block(&mut self, block: Block) -> Result<bool, PumpError> {
-        self.block_timestamps.insert(block.number, block.timestamp);
-        let pending_logs = match self.pending_logs.remove(&block.number) {
-            Some(pending_logs) => pending_logs,
-            None => return Ok(false),
+    /// Commits every pending block up to and including `number`, in order
+    async fn confirm_through(&mut self, number: u64) -> Result<(), PumpError> {
+        if number <= self.committed_to {
+            return Ok(());
+        }
+        let ready: Vec<u64> = self.pending_logs.range(..=number).map(|(n, _)| *n).collect();
+        for n in ready.iter().copied() {
+            let logs = self.pending_logs.remove(&n).unwrap_or_default();
+            if let Err(e) = self.commit_block(n, logs.clone()).await {
+                // keep the logs so we retry the block on the next header
+                self.pending_logs.insert(n, logs);
+                return Err(e);
+            }
+        }
+        if !ready.contains(&number) {
+            // nothing happened in this block, but the cursor still moves past it
+            self.commit_block(number, vec![]).await?;
+        }
+        Ok(())
+    }
+
+    fn publish(&mut self, update: ListenerUpdate, provisional: bool) {
+        let message = ListenerMessage { seq: self.next_seq, update, provisional };
+        // if the message is lost, the gap tells clients to resync
+        self.next_seq += 1;
+        if let Err(e) = self.publisher.publish(&message) {
+            log::error!("Error publishing update: {:?}", e);
+        }
+    }
+
+    /// Checks the new header against the blocks we've already seen,
+    /// rolling back orphaned blocks if the chain reorganized,
+    /// then commits confirmed blocks
+    pub async fn new_header(&mut self, header: Header) -> Result<(), PumpError> {
+        let (number, hash) = (header.number, header.hash);
+        if self.block_hashes.get(&number) == Some(&hash) {
+            log::debug!("Ignoring duplicate header for block {}", number);
+            return Ok(());
+        }
+
+        if let Some(fork) = self.find_fork(&header).await? {
+            self.rollback(fork).await?;
+        }
+
+        self.block_hashes.insert(number, hash);
+        if let Some(oldest) = number.checked_sub(REORG_WINDOW) {
+            self.block_hashes = self.block_hashes.split_off(&oldest);
+            self.journals = self.journals.split_off(&oldest);
+            self.block_timestamps.retain(|n, _| *n >= oldest);
+        }
+        self.new_block(header.into()).await
+    }
+
+    /// Returns the last block we've seen that is still canonical,
+    /// or None if the header extends the chain we're tracking
+    async fn find_fork(&self, header: &Header) -> Result<Option<u64>, PumpError> {
+        let number = header.number;
+        let extends_parent = match self.block_hashes.range(..number).next_back() {
+            Some((&parent, parent_hash)) if parent + 1 == number => {
+                *parent_hash == header.parent_hash
+            }
+            // we missed some blocks, e.g. while reconnecting
+            Some((&seen, seen_hash)) => {
+                self.client.get_block_header(seen).await?.hash == *seen_hash
+            }
+            None => true,
+        };
+        let replaces_seen = self.block_hashes.range(number..).next().is_some();
+        if extends_parent && !replaces_seen {
+            return Ok(None);
+        }
+
+        for (&seen, hash) in self.block_hashes.range(..number).rev() {
+            let canonical = self.client.get_block_header(seen).await?;
+            if canonical.hash == *hash {
+                return Ok(Some(seen));
+            }
+        }
+        let oldest = self.block_hashes.keys().next().copied().unwrap_or(number);
+        log::error!("Reorg at block {} is deeper than the blocks we track", number);
+        Ok(Some(oldest.saturating_sub(1)))
+    }
+
+    /// Undoes everything written for blocks after `fork`,
+    /// then replays the canonical chain from there
+    async fn rollback(&mut self, fork: u64) -> Result<(), PumpError> {
+        let orphaned = self.journals.split_off(&(fork + 1));
+        let depth = match self.block_hashes.keys().next_back() {
+            Some(&latest) if latest > fork => latest - fork,
+            _ => 0,
         };
-        let mut restart = false;
-        for log in pending_logs.wei_in_updates {
-            restart |= self.handle_wei_in_updated(log).await?;
+        log::warn!("Chain reorg detected: rolling back {} blocks after block {}", depth, fork);
+        let unjournaled = (fork + 1..=self.committed_to).filter(|n| !orphaned.contains_key(n));
+        if let Some(first) = unjournaled.min() {
+            // the canonical chain is still replayed below, but their orphaned rows stay
+            log::error!(
+                "Can't roll back blocks from {}, they were committed before the listener started",
+                first
+            );
         }
-        for log in pending_logs.deployed_to_dexs {
-            restart |= self.handle_deploy(log).await?;
+
+        let coin_ids: BTreeSet<i64> = orphaned.values().flat_map(|j| j.coin_ids()).collect();
+        // release the connection before backfilling, which can take a while
+        self.conn()?.transaction::<_, PumpError, _>(|conn| {
+            for journal in orphaned.values().rev() {
+                store::delete_trades(conn, &journal.trades)?;
+                store::delete_wei_in_updates(conn, &journal.wei_in_updates)?;
+                store::delete_prices(conn, &journal.candles)?;
+                for coin_id in journal.deployments.iter() {
+                    store::clear_deployed_pool(conn, *coin_id)?;
+                }
+                for coin_id in journal.graduations.iter() {
+                    store::revert_graduation(conn, *coin_id)?;
+                    store::delete_deployment_job(conn, *coin_id)?;
+                }
+                for (coin_id, wei_in) in journal.wei_ins.iter().rev() {
+                    store::update_wei_in(conn, *coin_id, wei_in.clone())?;
+                }
+            }
+            Ok(())
+        })?;
+
+        self.block_hashes.retain(|number, _| *number <= fork);
+        self.forget_blocks_after(fork);
+        if matches!(self.cursor()?, Some(cursor) if cursor > fork) {
+            let mut conn = self.conn()?;
+            store::reset_block_cursor(&mut conn, self.client.chain_id, fork)?;
         }
-        for log in pending_logs.syncs {
-            restart |= self.handle_sync(log).await?;
+
+        // re-apply the canonical chain
+        self.backfill().await?;
+
+        self.publish(ListenerUpdate::Reorg { fork_block: fork, depth }, false);
+        let mut conn = self.conn()?;
+        for coin_id in coin_ids {
+            let coin = store::get_coin(&mut conn, coin_id)?;
+            self.publish(ListenerUpdate::CorrectedCoin(coin), false);
         }
-        for log in pending_logs.swaps {
-            restart |= self.handle_swap(log).await?;
+        Ok(())
+    }
+
+    /// Drops everything buffered for blocks after `number`,
+    /// which must be replayed with `backfill` afterwards
+    fn forget_blocks_after(&mut self, number: u64) {
+        self.block_timestamps.retain(|n, _| *n <= number);
+        self.pending_logs.retain(|n, _| *n <= number);
+        self.synced_to = self.synced_to.min(number);
+        self.committed_to = self.committed_to.min(number);
+    }
+
+    /// Reconnects the websocket after one of its streams ends.
+    /// Blocks after the cursor may have been partially received,
+    /// so we forget them and let `backfill` replay them after resubscribing
+    pub async fn reconnect(&mut self) -> Result<(), PumpError> {
+        self.ws.reconnect().await;
+        self.forget_blocks_after(self.committed_to);
+        Ok(())
+    }
+
+    /// Commits every block that is now confirmed
+    pub async fn new_block(&mut self, block: Block) -> Result<(), PumpError> {
+        // deliver anything queued while the server was down
+        self.publisher.flush();
+        self.block_timestamps.insert(block.number, block.timestamp);
+        let confirmed = self.confirmed_height(block.number).await;
+        self.confirm_through(confirmed).await
+    }
+
+    /// The highest block we can write when the chain's head is at `head`:
+    /// `confirmations` blocks behind it, and no later than the `safe`/`finalized` block if configured
+    async fn confirmed_height(&self, head: u64) -> u64 {
+        let confirmed = head.saturating_sub(self.config.confirmations);
+        let tag = match self.config.finality.tag() {
+            Some(tag) => tag,
+            None => return confirmed,
+        };
+        match self.client.get_tagged_block_number(tag).await {
+            Ok(Some(tagged)) => confirmed.min(tagged),
+            Ok(None) => {
+                log::warn!(
+                    "Node has no {:?} block, using confirmation depth",
+                    self.config.finality
+                );
+                confirmed
+            }
+            Err(e) => {
+                log::warn!("Error fetching {:?} block: {:?}", self.config.finality, e);
+                confirmed
+            }
         }
-        Ok(restart)
-    }
-
-    async fn flush_confirmed_block(&mut self, number: u64) -> Result<bool, PumpError> {
-        let restart = match self.pending_logs.get(&number).is_some() {
-            false => false,
-            true => {
-                let confirmed_block = match self.block_timestamps.get(&number) {
-                    Some(timestamp) => Block { number, timestamp: *timestamp as i64 },
-                    None => {
-                        // if we have logs in the confirmed block, but no timestamp,
-                        // then request the timestamp from the RPC
-                        let header = self.client.get_block_header(number).await?;
-                        Block { number, timestamp: header.timestamp as i64 }
-                    }
-                };
-                self.flush_block(confirmed_block).await?
+    }
+
+    fn cursor(&self) -> Result<Option<u64>, PumpError> {
+        let mut conn = self.conn()?;
+        store::get_block_cursor(&mut conn, self.client.chain_id)
+    }
+
+    fn set_cursor(&self, block_number: u64) -> Result<(), PumpError> {
+        let mut conn = self.conn()?;
+        store::set_block_cursor(&mut conn, self.client.chain_id, block_number)
+    }
+
+    /// Fetches logs from every block after the persisted cursor up to the chain head,
+    /// so events emitted while the listener was down aren't lost,
+    /// and commits the blocks that are already confirmed.
+    /// Live logs at or below the backfilled height are skipped afterwards
+    pub async fn backfill(&mut self) -> Result<(), PumpError> {
+        let head = self.client.get_block_number().await?;
+        let from_block = match self.cursor()? {
+            Some(cursor) => {
+                self.committed_to = cursor;
+                (cursor + 1).max(self.synced_to + 1)
+            }
+            None => {
+                log::info!("No block cursor found, starting from block {}", head);
+                self.set_cursor(head)?;
+                self.synced_to = head;
+                self.committed_to = head;
+                return Ok(());
             }
         };
+        if from_block > head {
+            return Ok(());
+        }
 
-        let (confirmed, block_prices) = self.prices.remove(&number).unwrap_or_default();
-        for (pool, prices) in block_prices {
-            // this function is &mut self
-            self.flush_candle(pool, prices, confirmed).await?;
+        log::info!("Backfilling blocks {} to {}", from_block, head);
+        let confirmed = self.confirmed_height(head).await;
+        let mut chunk_start = from_block;
+        while chunk_start <= head {
+            let chunk_end = (chunk_start + self.config.backfill_chunk_size - 1).min(head);
+            self.backfill_range(chunk_start, chunk_end).await?;
+            self.synced_to = chunk_end;
+            // blocks past the confirmed height stay buffered until new headers confirm them
+            self.confirm_through(chunk_end.min(confirmed)).await?;
+            chunk_start = chunk_end + 1;
         }
-        Ok(restart)
+        log::info!("Backfilled through block {}, committed through {}", head, self.committed_to);
+        Ok(())
     }
 
-    /// flush all of the candles we created for N=2 blocks ago
-    pub async fn new_block(&mut self, block: Block) -> Result<bool, PumpError> {
-        let mut restart = false;
-        if let Some(number) = block.number.checked_sub(CONFIRMATIONS) {
-            restart = self.flush_confirmed_block(number).await?;
+    async fn backfill_range(&mut self, from_block: u64, to_block: u64) -> Result<(), PumpError> {
+        let mut logs = self.client.get_pump_logs(from_block, to_block).await?;
+        logs.extend(self.client.get_pair_logs(from_block, to_block).await?);
+
+        let mut blocks: BTreeSet<u64> = BTreeSet::new();
+        for log in logs {
+            if log.removed {
+                continue;
+            }
+            blocks.insert(log.block_number.ok_or_else(PumpError::no_block_number)?);
+            match self.buffer_log(log).await {
+                Ok(()) => {}
+                // these will never succeed, so don't hold up the chunk
+                Err(PumpError::Listener(e)) => log::error!("Skipping backfilled log: {:?}", e),
+                // the cursor stays put, so the chunk is fetched again
+                Err(e) => return Err(e),
+            }
+        }
+
+        for number in blocks {
+            let header = self.client.get_block_header(number).await?;
+            self.block_timestamps.insert(number, header.timestamp as i64);
+            self.block_hashes.insert(number, header.hash);
         }
-        restart |= self.flush_block(block).await?;
-        Ok(restart)
+        Ok(())
     }
 
     pub fn conn(&self) -> Result<PgConn, PumpError> {
@@ -465,19 +813,11 @@ impl LogHandler {
         self.ws.blocks().await
     }
 
-    fn lp_tokens(&self) -> Vec<Address> {
-        self.pools.iter().map(|(lp, _)| lp.clone()).collect()
-    }
-
     pub async fn pump_stream(&self) -> Result<SubscriptionStream<Log>, PumpError> {
         self.ws.pump_logs().await
     }
 
     pub async fn pairs_stream(&self) -> Result<SubscriptionStream<Log>, PumpError> {
-        self.ws.pair_logs(self.lp_tokens()).await
-    }
-
-    pub async fn unsubscribe(&self, id: FixedBytes<32>) -> Result<(), PumpError> {
-        self.ws.unsubscribe(id).await
+        self.ws.pair_logs().await
     }
 }
@@ -0,0 +1,277 @@
+use alloy_primitives::B256;
+use bigdecimal::{BigDecimal, ToPrimitive};
+use chrono::{NaiveDateTime, Utc};
+use std::{str::FromStr, sync::Arc, time::Duration};
+use tokio::sync::Notify;
+
+use pump::{
+    client::{
+        tx::{PendingTx, TxManager, TxOutcome},
+        PumpClient,
+    },
+    db::{
+        models::{DeploymentJob, DeploymentJobChanges, DeploymentStatus},
+        pool::{connect, PgConn, PgPool},
+        store,
+    },
+    error::PumpError,
+};
+
+use crate::{config::KeeperConfig, handler::fmt_hex};
+
+/// how many jobs to work on each time the keeper wakes up
+const BATCH_SIZE: i64 = 20;
+const MAX_BACKOFF: Duration = Duration::from_secs(600);
+
+/// Deploys graduated coins to the dex. Each coin gets a job in the DB,
+/// so deployments survive restarts and failed txs are retried
+pub(crate) struct Keeper {
+    pool: PgPool,
+    client: PumpClient,
+    /// the only sender of the deployer's txs, so it owns the deployer's nonce
+    txs: TxManager,
+    config: KeeperConfig,
+    /// notified by the log handler when coins graduate
+    wake: Arc<Notify>,
+}
+
+fn now() -> NaiveDateTime {
+    Utc::now().naive_utc()
+}
+
+fn after(delay: Duration) -> NaiveDateTime {
+    now() + chrono::Duration::from_std(delay).unwrap_or(chrono::Duration::zero())
+}
+
+/// The job's last tx, if it was sent. Jobs sent before we stored fees get 0,
+/// so a replacement bumps the node's current estimate instead
+fn sent_tx(job: &DeploymentJob) -> Option<PendingTx> {
+    let hash = job.tx.as_deref().and_then(|tx| B256::from_str(tx).ok())?;
+    let fee = |fee: &Option<BigDecimal>| fee.as_ref().and_then(|fee| fee.to_u128()).unwrap_or(0);
+    Some(PendingTx {
+        hash,
+        nonce: job.nonce? as u64,
+        max_fee_per_gas: fee(&job.max_fee_per_gas),
+        max_priority_fee_per_gas: fee(&job.max_priority_fee_per_gas),
+    })
+}
+
+impl Keeper {
+    pub fn new(pool: PgPool, client: PumpClient, txs: TxManager, wake: Arc<Notify>) -> Keeper {
+        let config = KeeperConfig::from_env();
+        log::info!(
+            "Deployment keeper sending from {} every {:?}",
+            txs.address(),
+            config.poll_interval
+        );
+        Keeper { pool, client, txs, config, wake }
+    }
+
+    pub async fn run(self) {
+        loop {
+            if let Err(e) = self.tick().await {
+                log::error!("Deployment keeper error: {:?}", e);
+            }
+            tokio::select! {
+                _ = self.wake.notified() => {},
+                _ = tokio::time::sleep(self.config.poll_interval) => {},
+            }
+        }
+    }
+
+    fn conn(&self) -> Result<PgConn, PumpError> {
+        Ok(connect(&self.pool)?)
+    }
+
+    async fn tick(&self) -> Result<(), PumpError> {
+        let jobs = {
+            let mut conn = self.conn()?;
+            let queued = store::enqueue_undeployed(&mut conn)?;
+            if queued > 0 {
+                log::warn!("Queued {} graduated coins that had no deployment job", queued);
+            }
+            store::complete_deployments(&mut conn)?;
+            store::get_due_deployments(&mut conn, BATCH_SIZE)?
+        };
+        for job in jobs {
+            let coin_id = job.coin_id;
+            if let Err(e) = self.work(job).await {
+                log::error!("Error deploying coin {}: {:?}", coin_id, e);
+            }
+        }
+        Ok(())
+    }
+
+    async fn work(&self, job: DeploymentJob) -> Result<(), PumpError> {
+        match job.status() {
+            DeploymentStatus::Submitted => self.check_receipt(job).await,
+            _ => self.submit(job).await,
+        }
+    }
+
+    /// Waits on the job's tx, replacing it if it's stuck
+    async fn check_receipt(&self, job: DeploymentJob) -> Result<(), PumpError> {
+        let sent = match sent_tx(&job) {
+            Some(sent) => sent,
+            None => return self.submit(job).await,
+        };
+        match self.txs.poll(&sent).await? {
+            TxOutcome::Confirmed(receipt) => {
+                log::info!(
+                    "Deployed coin {} to the dex in block {:?}, tx={}",
+                    job.coin_id,
+                    receipt.block_number,
+                    fmt_hex(receipt.hash)
+                );
+                self.update(
+                    job.coin_id,
+                    DeploymentJobChanges {
+                        status: Some(DeploymentStatus::Deployed.as_str().to_string()),
+                        last_error: Some(None),
+                        ..Default::default()
+                    },
+                )
+            }
+            TxOutcome::Reverted(receipt) => {
+                // the next attempt is a new tx, so it needs a new nonce
+                let error = format!("tx {} reverted", fmt_hex(receipt.hash));
+                self.retry(&job, error, None, job.attempts)
+            }
+            TxOutcome::Mined(_) => self.check_again(job.coin_id),
+            TxOutcome::Pending(_) => {
+                let submitted_at = job.submitted_at.unwrap_or(job.updated_at);
+                let waited = (now() - submitted_at).to_std().unwrap_or_default();
+                if waited < self.config.stuck_after {
+                    return self.check_again(job.coin_id);
+                }
+                log::warn!(
+                    "deployGraduated({}) tx {} not mined after {:?}, replacing it",
+                    job.coin_id,
+                    fmt_hex(sent.hash),
+                    waited
+                );
+                self.submit(job).await
+            }
+        }
+    }
+
+    fn check_again(&self, coin_id: i64) -> Result<(), PumpError> {
+        self.update(
+            coin_id,
+            DeploymentJobChanges {
+                next_attempt_at: Some(after(self.config.poll_interval)),
+                ..Default::default()
+            },
+        )
+    }
+
+    /// Sends deployGraduated, unless the coin can't or needn't be deployed.
+    /// If the job's last tx may still be pending, replaces it rather than sending a duplicate
+    async fn submit(&self, job: DeploymentJob) -> Result<(), PumpError> {
+        let coin_id = job.coin_id;
+        if job.attempts >= self.config.max_attempts {
+            log::error!(
+                "Giving up on deploying coin {} after {} attempts: {:?}",
+                coin_id,
+                job.attempts,
+                job.last_error
+            );
+            return self.update(
+                coin_id,
+                DeploymentJobChanges {
+                    status: Some(DeploymentStatus::Failed.as_str().to_string()),
+                    ..Default::default()
+                },
+            );
+        }
+
+        let coin = store::get_coin(&mut self.conn()?, coin_id)?;
+        if !coin.graduated {
+            // its graduation was orphaned by a reorg
+            log::warn!("Coin {} is no longer graduated, dropping its deployment", coin_id);
+            return store::delete_deployment_job(&mut self.conn()?, coin_id);
+        }
+        if let Some(pair) = self.client.get_deployed_pair(coin_id as u32).await? {
+            log::info!("Coin {} already has liquidity in {}, not redeploying", coin_id, pair);
+            return self.update(
+                coin_id,
+                DeploymentJobChanges {
+                    status: Some(DeploymentStatus::Deployed.as_str().to_string()),
+                    ..Default::default()
+                },
+            );
+        }
+
+        let stuck = sent_tx(&job);
+        let sent = match &stuck {
+            Some(stuck) => {
+                self.client.replace_deploy_graduated(&self.txs, coin_id as u32, stuck).await
+            }
+            None => self.client.send_deploy_graduated(&self.txs, coin_id as u32).await,
+        };
+        match sent {
+            Ok(sent) => {
+                log::info!(
+                    "Called deployGraduated({}), attempt {}, nonce {}, tx={}",
+                    coin_id,
+                    job.attempts + 1,
+                    sent.nonce,
+                    fmt_hex(sent.hash)
+                );
+                self.update(
+                    coin_id,
+                    DeploymentJobChanges {
+                        status: Some(DeploymentStatus::Submitted.as_str().to_string()),
+                        tx: Some(Some(sent.hash.to_string())),
+                        nonce: Some(Some(sent.nonce as i64)),
+                        max_fee_per_gas: Some(Some(BigDecimal::from(sent.max_fee_per_gas))),
+                        max_priority_fee_per_gas: Some(Some(BigDecimal::from(
+                            sent.max_priority_fee_per_gas,
+                        ))),
+                        attempts: Some(job.attempts + 1),
+                        next_attempt_at: Some(after(self.config.poll_interval)),
+                        submitted_at: Some(Some(now())),
+                        ..Default::default()
+                    },
+                )
+            }
+            // keep the nonce only if we were replacing a tx that may still be mined
+            Err(e) => self.retry(&job, format!("{:?}", e), job.nonce, job.attempts + 1),
+        }
+    }
+
+    /// Schedules the job to be sent again with exponential backoff.
+    /// `attempts` counts the txs sent for it, including any that failed to send
+    fn retry(
+        &self,
+        job: &DeploymentJob,
+        error: String,
+        nonce: Option<i64>,
+        attempts: i32,
+    ) -> Result<(), PumpError> {
+        let factor = 1u32 << attempts.clamp(0, 16) as u32;
+        let backoff = self.config.poll_interval.saturating_mul(factor).min(MAX_BACKOFF);
+        log::warn!(
+            "Deploying coin {} failed on attempt {}, retrying in {:?}: {}",
+            job.coin_id,
+            attempts,
+            backoff,
+            error
+        );
+        self.update(
+            job.coin_id,
+            DeploymentJobChanges {
+                status: Some(DeploymentStatus::Pending.as_str().to_string()),
+                nonce: Some(nonce),
+                attempts: Some(attempts),
+                last_error: Some(Some(error)),
+                next_attempt_at: Some(after(backoff)),
+                ..Default::default()
+            },
+        )
+    }
+
+    fn update(&self, coin_id: i64, changes: DeploymentJobChanges) -> Result<(), PumpError> {
+        store::update_deployment_job(&mut self.conn()?, coin_id, changes)
+    }
+}
@@ -1,42 +1,84 @@
+mod block;
+mod config;
 mod handler;
+mod keeper;
+mod publish;
 mod sock;
 
 use futures_util::{select, stream::StreamExt};
 use handler::LogHandler;
+use keeper::Keeper;
 use pump::{
-    client::{block::Block, PumpClient},
+    client::{block::Block, tx::TxManager, PumpClient},
     db::pool::establish_pool,
     error::PumpError,
     get_workspace_root,
 };
+use std::{sync::Arc, time::Duration};
+use tokio::sync::Notify;
+
+const RESUBSCRIBE_BASE_DELAY: Duration = Duration::from_millis(500);
+const RESUBSCRIBE_MAX_DELAY: Duration = Duration::from_secs(30);
 
 async fn run() -> Result<(), PumpError> {
     let rpc_url = std::env::var("RPC_URL").expect("Must set RPC_URL in .env");
     let client = PumpClient::new(&rpc_url).await?;
     let db_pool = establish_pool();
-    let mut handler = LogHandler::new(db_pool, client).await?;
 
+    // the keeper is the only part of the listener that sends txs
+    let wake_keeper = Arc::new(Notify::new());
+    let keeper = Keeper::new(
+        db_pool.clone(),
+        PumpClient::new(&rpc_url).await?,
+        TxManager::from_env(&rpc_url),
+        wake_keeper.clone(),
+    );
+    tokio::spawn(keeper.run());
+
+    let mut handler = LogHandler::new(db_pool, client, wake_keeper).await?;
+
+    // catch up on anything we missed while we were down
+    handler.backfill().await?;
+
+    let mut delay = RESUBSCRIBE_BASE_DELAY;
+    loop {
+        match listen(&mut handler).await {
+            Ok(()) => {
+                log::warn!("Websocket subscriptions dropped, reconnecting...");
+                delay = RESUBSCRIBE_BASE_DELAY;
+            }
+            Err(e) => {
+                // e.g. the node dropped us again while we subscribed or backfilled
+                log::error!("Error resubscribing, reconnecting in {:?}: {:?}", delay, e);
+                tokio::time::sleep(delay).await;
+                delay = (delay * 2).min(RESUBSCRIBE_MAX_DELAY);
+            }
+        }
+        handler.reconnect().await?;
+    }
+}
+
+/// Subscribes to blocks and logs, handling them until any of the streams ends
+async fn listen(handler: &mut LogHandler) -> Result<(), PumpError> {
     log::info!("Initializing pubsub streams");
     let mut block_stream = handler.block_stream().await?.fuse();
     let mut pump_stream = handler.pump_stream().await?.fuse();
     let mut pairs_stream = handler.pairs_stream().await?.fuse();
 
+    // replay blocks mined while we were subscribing;
+    // live logs from these blocks will be skipped by the handler
+    handler.backfill().await?;
+
     log::info!("Listening to streams...");
     loop {
         select! {
             maybe_block = block_stream.next() => {
                 match maybe_block {
-                    Some(block) => {
-                        let block: Block = block.into();
+                    Some(header) => {
+                        let block: Block = header.clone().into();
                         log::debug!("Received block: {:?}", block);
-                        match handler.new_block(block).await {
-                            Ok(false) => {}
-                            Ok(true) => {
-                                log::debug!("Resubscribing to pairs stream");
-                                handler.unsubscribe(pairs_stream.into_inner().id().clone()).await?;
-                                pairs_stream = handler.pairs_stream().await?.fuse();
-                            }
-                            Err(e) => { log::error!("Error flushing prices for block {}: {:?}", block.number, e); }
+                        if let Err(e) = handler.new_header(header).await {
+                            log::error!("Error committing blocks through {}: {:?}", block.number, e);
                         }
                     },
                     None => {
@@ -48,17 +90,8 @@ async fn run() -> Result<(), PumpError> {
             maybe_log = pump_stream.next() => {
                 match maybe_log {
                     Some(log) => {
-                        match handler.handle_log(log).await {
-                            Ok(false) =>  {}
-                            Ok(true) => {
-                                // restart the stream
-                                log::debug!("Resubscribing to pairs stream");
-                                handler.unsubscribe(pairs_stream.into_inner().id().clone()).await?;
-                                pairs_stream = handler.pairs_stream().await?.fuse();
-                            }
-                            Err(e) => {
-                                log::error!("Error handling log: {:?}", e);
-                            }
+                        if let Err(e) = handler.handle_log(log).await {
+                            log::error!("Error handling log: {:?}", e);
                         }
                     },
                     None => {
@@ -70,11 +103,8 @@ async fn run() -> Result<(), PumpError> {
             maybe_log = pairs_stream.next() => {
                 match maybe_log {
                     Some(log) => {
-                        match handler.handle_log(log).await {
-                            Err(e) => {
-                                log::error!("Error handling log: {:?}", e);
-                            }
-                            Ok(_) => {}
+                        if let Err(e) = handler.handle_log(log).await {
+                            log::error!("Error handling log: {:?}", e);
                         }
                     },
                     None => {
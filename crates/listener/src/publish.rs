@@ -0,0 +1,54 @@
+use diesel::PgConnection;
+
+use pump::{
+    db::{
+        pool::{connect, PgPool},
+        store,
+    },
+    error::{ListenerError, PumpError},
+    ListenerMessage, UpdateTransport, NOTIFY_CHANNEL,
+};
+
+use crate::sock::SockWriter;
+
+/// Sends updates to the server over the configured transport
+pub(crate) enum Publisher {
+    Socket(SockWriter),
+    Postgres(PgPool),
+}
+
+impl Publisher {
+    pub fn new(transport: UpdateTransport, pool: PgPool) -> Publisher {
+        match transport {
+            UpdateTransport::Socket => Publisher::Socket(SockWriter::new()),
+            UpdateTransport::Postgres => Publisher::Postgres(pool),
+        }
+    }
+
+    /// Whether a block's updates should be sent with `notify` inside its transaction
+    pub fn in_transaction(&self) -> bool {
+        matches!(self, Publisher::Postgres(_))
+    }
+
+    /// Sends a message on its own, outside of any block's transaction
+    pub fn publish(&mut self, message: &ListenerMessage) -> Result<(), PumpError> {
+        match self {
+            Publisher::Socket(sock_writer) => Ok(sock_writer.write(message)?),
+            Publisher::Postgres(pool) => notify(&mut connect(pool)?, message),
+        }
+    }
+
+    /// Delivers anything the socket queued while the server was down
+    pub fn flush(&mut self) {
+        if let Publisher::Socket(sock_writer) = self {
+            sock_writer.flush();
+        }
+    }
+}
+
+/// Queues the message as a Postgres notification.
+/// Inside a transaction, the server only receives it if the transaction commits
+pub(crate) fn notify(conn: &mut PgConnection, message: &ListenerMessage) -> Result<(), PumpError> {
+    let payload = serde_json::to_string(message).map_err(ListenerError::from)?;
+    store::notify(conn, NOTIFY_CHANNEL, &payload)
+}
@@ -1,52 +1,114 @@
-use std::{io::prelude::*, os::unix::net::UnixStream};
-
-use alloy_primitives::Address;
-use bigdecimal::BigDecimal;
-use pump::{
-    db::models::Coin, error::ListenerError, get_workspace_root, ListenerUpdate, SOCKET_FILENAME,
+use std::{
+    collections::VecDeque,
+    io::prelude::*,
+    os::unix::net::UnixStream,
+    time::{Duration, Instant},
 };
 
+use pump::{error::ListenerError, get_workspace_root, ListenerMessage, SOCKET_FILENAME};
+
+const RECONNECT_BASE_DELAY: Duration = Duration::from_millis(500);
+const RECONNECT_MAX_DELAY: Duration = Duration::from_secs(30);
+/// so a stalled server can't block the listener
+const WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+/// updates we hold onto while the server is down before dropping the oldest
+const MAX_QUEUED_FRAMES: usize = 10_000;
+
+/// Writes updates to the server's unix socket.
+/// Frames are queued and delivered in order, reconnecting with backoff if the server goes away
 pub struct SockWriter {
-    stream: UnixStream,
+    path: String,
+    stream: Option<UnixStream>,
+    queue: VecDeque<Vec<u8>>,
+    reconnect_delay: Duration,
+    /// don't try to reconnect before this
+    retry_at: Option<Instant>,
 }
 
 impl SockWriter {
-    pub(crate) fn try_new() -> Result<Self, ListenerError> {
+    pub(crate) fn new() -> Self {
         let path = format!("{}/{}", get_workspace_root().unwrap(), SOCKET_FILENAME);
-        let stream = UnixStream::connect(&path)?;
-        Ok(Self { stream })
+        Self {
+            path,
+            stream: None,
+            queue: VecDeque::new(),
+            reconnect_delay: RECONNECT_BASE_DELAY,
+            retry_at: None,
+        }
     }
 
-    fn write(&mut self, message: &ListenerUpdate) -> Result<(), ListenerError> {
-        let message = serde_json::to_string(message)?;
-        self.stream.write_all(message.as_bytes())?;
+    /// Queues the message as a single line of JSON, then sends everything we can.
+    /// Compact JSON never contains a raw newline, so the server splits frames on them
+    pub(crate) fn write(&mut self, message: &ListenerMessage) -> Result<(), ListenerError> {
+        let mut frame = serde_json::to_vec(message)?;
+        frame.push(b'\n');
+        if self.queue.len() >= MAX_QUEUED_FRAMES {
+            log::warn!("Socket queue is full, dropping the oldest update");
+            self.queue.pop_front();
+        }
+        self.queue.push_back(frame);
+        self.flush();
         Ok(())
     }
 
-    pub(crate) fn write_verified_coin(&mut self, coin: Coin) -> Result<(), ListenerError> {
-        self.write(&ListenerUpdate::VerifiedCoin(coin))
+    /// Sends queued frames in order until the queue is empty or the socket breaks
+    pub(crate) fn flush(&mut self) {
+        while !self.queue.is_empty() && self.ensure_connected() {
+            let result = match (self.stream.as_mut(), self.queue.front()) {
+                (Some(stream), Some(frame)) => stream.write_all(frame),
+                _ => return,
+            };
+            if let Err(e) = result {
+                // the frame stays queued, and is resent whole over the next connection
+                log::error!(
+                    "Error writing to socket, {} updates queued: {:?}",
+                    self.queue.len(),
+                    e
+                );
+                self.disconnect();
+                return;
+            }
+            self.queue.pop_front();
+        }
     }
 
-    pub(crate) fn write_wei_in_updated(
-        &mut self,
-        coin_id: i64,
-        total_wei_in: BigDecimal,
-    ) -> Result<(), ListenerError> {
-        self.write(&ListenerUpdate::WeiInUpdated { id: coin_id, wei_in: total_wei_in })
+    /// Connects if we aren't connected and the backoff has passed
+    fn ensure_connected(&mut self) -> bool {
+        if self.stream.is_some() {
+            return true;
+        }
+        if matches!(self.retry_at, Some(retry_at) if Instant::now() < retry_at) {
+            return false;
+        }
+        match self.connect() {
+            Ok(stream) => {
+                log::info!("Connected to socket at {}", self.path);
+                self.stream = Some(stream);
+                self.reconnect_delay = RECONNECT_BASE_DELAY;
+                self.retry_at = None;
+                true
+            }
+            Err(e) => {
+                log::warn!(
+                    "Error connecting to socket, retrying in {:?}: {:?}",
+                    self.reconnect_delay,
+                    e
+                );
+                self.disconnect();
+                false
+            }
+        }
     }
 
-    pub(crate) fn write_graduated_coin(&mut self, coin_id: i64) -> Result<(), ListenerError> {
-        self.write(&ListenerUpdate::GraduatedCoin { id: coin_id })
+    fn connect(&self) -> Result<UnixStream, ListenerError> {
+        let stream = UnixStream::connect(&self.path)?;
+        stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+        Ok(stream)
     }
 
-    pub(crate) fn write_deployed_to_dex(
-        &mut self,
-        coin_id: i64,
-        deployed_pool: Address,
-    ) -> Result<(), ListenerError> {
-        self.write(&ListenerUpdate::DeployedToDex {
-            id: coin_id,
-            deployed_pool: deployed_pool.to_string(),
-        })
+    fn disconnect(&mut self) {
+        self.stream = None;
+        self.retry_at = Some(Instant::now() + self.reconnect_delay);
+        self.reconnect_delay = (self.reconnect_delay * 2).min(RECONNECT_MAX_DELAY);
     }
 }
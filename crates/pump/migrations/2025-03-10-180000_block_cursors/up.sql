@@ -0,0 +1,11 @@
+-- Your SQL goes here
+CREATE TABLE block_cursors (
+    -- One cursor per chain the listener indexes
+    chain_id INT PRIMARY KEY,
+
+    -- The last block whose logs have been fully processed
+    block_number BIGINT NOT NULL,
+
+    -- Timestamp for when the cursor last advanced
+    updated_at TIMESTAMP DEFAULT NOW() NOT NULL
+);
@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE trades DROP CONSTRAINT IF EXISTS trades_log_index_not_null;
+ALTER TABLE trades DROP CONSTRAINT IF EXISTS uq_trades_tx_log_index;
+ALTER TABLE trades DROP COLUMN IF EXISTS block_number;
+ALTER TABLE trades DROP COLUMN IF EXISTS log_index;
@@ -0,0 +1,28 @@
+-- Your SQL goes here
+
+-- Replays used to insert the same swap more than once, so keep the first copy.
+-- Copies match in every column but id. Without log indexes we can't tell a replay
+-- from identical swaps in the same block, so only rows identical down to the time go
+DELETE FROM trades t
+USING trades dup
+WHERE t.id > dup.id
+    AND t.tx = dup.tx
+    AND t.pool = dup.pool
+    AND t.trader = dup.trader
+    AND t.buy_0 = dup.buy_0
+    AND t.amount_0 = dup.amount_0
+    AND t.amount_1 = dup.amount_1
+    AND t.time = dup.time;
+
+-- Position of the Swap log, so each log maps to exactly one trade.
+-- These are null for trades recorded before this migration
+ALTER TABLE trades ADD COLUMN log_index BIGINT;
+ALTER TABLE trades ADD COLUMN block_number BIGINT;
+
+ALTER TABLE trades
+ADD CONSTRAINT uq_trades_tx_log_index UNIQUE (tx, log_index);
+
+-- NULLs never conflict, so every new trade needs its log index.
+-- NOT VALID skips the check for trades recorded before this migration
+ALTER TABLE trades
+ADD CONSTRAINT trades_log_index_not_null CHECK (log_index IS NOT NULL) NOT VALID;
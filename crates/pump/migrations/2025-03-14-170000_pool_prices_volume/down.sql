@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE pool_prices
+    DROP COLUMN IF EXISTS volume_base,
+    DROP COLUMN IF EXISTS volume_quote,
+    DROP COLUMN IF EXISTS trade_count;
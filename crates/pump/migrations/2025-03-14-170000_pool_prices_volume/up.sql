@@ -0,0 +1,7 @@
+-- Your SQL goes here
+ALTER TABLE pool_prices
+    -- Volume traded in the block, in whole tokens of the coin
+    ADD COLUMN volume_base DECIMAL NOT NULL DEFAULT 0,
+    -- Volume traded in the block, in ETH
+    ADD COLUMN volume_quote DECIMAL NOT NULL DEFAULT 0,
+    ADD COLUMN trade_count INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX IF EXISTS idx_trades_pool_time;
+DROP INDEX IF EXISTS idx_trades_trader_time;
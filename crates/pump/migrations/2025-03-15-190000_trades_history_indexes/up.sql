@@ -0,0 +1,3 @@
+-- Your SQL goes here
+CREATE INDEX idx_trades_pool_time ON trades(pool, time DESC, id DESC);
+CREATE INDEX idx_trades_trader_time ON trades(trader, time DESC, id DESC);
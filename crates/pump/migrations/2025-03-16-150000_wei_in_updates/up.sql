@@ -0,0 +1,16 @@
+-- Your SQL goes here
+CREATE TABLE wei_in_updates (
+    id BIGSERIAL PRIMARY KEY,
+    coin_id BIGINT NOT NULL REFERENCES coins(id) ON DELETE CASCADE,
+    block_number BIGINT NOT NULL,
+    log_index BIGINT NOT NULL,
+    tx VARCHAR(66) NOT NULL,
+    time BIGINT NOT NULL,
+    -- total wei in after the update
+    wei_in NUMERIC NOT NULL,
+    -- positive for buys, negative for refunds
+    delta NUMERIC NOT NULL,
+    CONSTRAINT uq_wei_in_updates_tx_log_index UNIQUE (tx, log_index)
+);
+
+CREATE INDEX idx_wei_in_updates_coin_block ON wei_in_updates(coin_id, block_number, log_index);
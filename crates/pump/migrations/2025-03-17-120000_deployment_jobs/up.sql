@@ -0,0 +1,24 @@
+-- Your SQL goes here
+-- One job per graduated coin, tracking the deployGraduated tx that moves it to the dex
+CREATE TABLE deployment_jobs (
+    coin_id BIGINT PRIMARY KEY REFERENCES coins(id) ON DELETE CASCADE,
+    -- pending, submitted, deployed or failed
+    status VARCHAR(16) NOT NULL DEFAULT 'pending',
+    -- the latest tx sent for this job
+    tx VARCHAR(66),
+    nonce BIGINT,
+    attempts INT NOT NULL DEFAULT 0,
+    last_error TEXT,
+    next_attempt_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    submitted_at TIMESTAMP,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_deployment_jobs_status_next_attempt
+ON deployment_jobs(status, next_attempt_at);
+
+-- Queue coins that graduated but were never deployed
+INSERT INTO deployment_jobs (coin_id)
+SELECT id FROM coins
+WHERE graduated = true AND deployed_pool IS NULL;
@@ -0,0 +1,12 @@
+-- Your SQL goes here
+-- Keys for the privileged endpoints. Only the keccak256 hash of each key is stored
+CREATE TABLE api_keys (
+    id BIGSERIAL PRIMARY KEY,
+    name TEXT NOT NULL,
+    key_hash CHAR(66) NOT NULL UNIQUE,
+    -- e.g. verify, sync, deploy, or admin for all of them
+    scopes TEXT[] NOT NULL DEFAULT '{}',
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    last_used_at TIMESTAMP,
+    revoked_at TIMESTAMP
+);
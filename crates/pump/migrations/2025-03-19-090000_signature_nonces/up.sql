@@ -0,0 +1,12 @@
+-- Your SQL goes here
+-- Nonces from signed coin metadata writes, so each signature is only used once.
+-- Rows can be deleted once they expire, since the signature is rejected after that
+CREATE TABLE signature_nonces (
+    signer CHAR(42) NOT NULL,
+    nonce VARCHAR(128) NOT NULL,
+    expires_at TIMESTAMP NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (signer, nonce)
+);
+
+CREATE INDEX idx_signature_nonces_expires_at ON signature_nonces(expires_at);
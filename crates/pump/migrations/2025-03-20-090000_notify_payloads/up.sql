@@ -0,0 +1,10 @@
+-- Your SQL goes here
+-- Messages too big for a Postgres notification. The notification carries the row's id instead.
+-- Every server fetches the row, so it's only deleted once it's a day old
+CREATE TABLE notify_payloads (
+    id BIGSERIAL PRIMARY KEY,
+    payload TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_notify_payloads_created_at ON notify_payloads(created_at);
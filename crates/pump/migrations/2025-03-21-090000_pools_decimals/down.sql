@@ -0,0 +1,2 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE pools DROP COLUMN decimals_0, DROP COLUMN decimals_1;
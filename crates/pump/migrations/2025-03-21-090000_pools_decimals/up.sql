@@ -0,0 +1,5 @@
+-- Your SQL goes here
+-- Every pool so far pairs WETH with an 18 decimal coin
+ALTER TABLE pools
+    ADD COLUMN decimals_0 INTEGER NOT NULL DEFAULT 18,
+    ADD COLUMN decimals_1 INTEGER NOT NULL DEFAULT 18;
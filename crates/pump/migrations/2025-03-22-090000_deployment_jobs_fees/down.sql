@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE deployment_jobs
+    DROP COLUMN IF EXISTS max_fee_per_gas,
+    DROP COLUMN IF EXISTS max_priority_fee_per_gas;
@@ -0,0 +1,5 @@
+-- Your SQL goes here
+ALTER TABLE deployment_jobs
+    -- fees the latest tx was sent with, so a replacement can outbid it after a restart
+    ADD COLUMN max_fee_per_gas NUMERIC,
+    ADD COLUMN max_priority_fee_per_gas NUMERIC;
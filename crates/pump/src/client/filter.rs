@@ -0,0 +1,19 @@
+use alloy_primitives::Address;
+use alloy_rpc_types_eth::Filter;
+use alloy_sol_types::SolEvent;
+
+use crate::contract::pair::UniswapV2Pair;
+
+/// Matches every event emitted by the PumpRand contract
+pub fn pump_filter(pump: Address) -> Filter {
+    Filter::new().address(pump)
+}
+
+/// Matches Swap and Sync events emitted by any pair,
+/// so callers must filter by the pools they are tracking
+pub fn pair_events_filter() -> Filter {
+    Filter::new().event_signature(vec![
+        UniswapV2Pair::Swap::SIGNATURE_HASH,
+        UniswapV2Pair::Sync::SIGNATURE_HASH,
+    ])
+}
@@ -1,22 +1,26 @@
-use alloy_network::EthereumWallet;
-use alloy_primitives::{hex::FromHex, Address, FixedBytes, B256};
-use alloy_provider::{
-    network::TransactionBuilder, Provider, SeismicSignedProvider, SeismicUnsignedProvider,
+use alloy_primitives::Address;
+use alloy_provider::{network::TransactionBuilder, Provider, SeismicUnsignedProvider};
+use alloy_rpc_types_eth::{BlockNumberOrTag, Header, Log, TransactionInput, TransactionRequest};
+use alloy_sol_types::{
+    sol_data::{Address as SolAddress, Bool, Uint},
+    SolType,
 };
-use alloy_rpc_types_eth::{Header, TransactionInput, TransactionRequest};
-use alloy_signer_local::LocalSigner;
-use alloy_sol_types::{sol_data::Bool, SolType};
-use alloy_transport::TransportError;
 use reqwest::Url;
 use std::str::FromStr;
 
 use crate::{
-    client::{contract_address::ContractAddresses, pool::Pool},
+    client::{
+        contract_address::ContractAddresses,
+        filter::{pair_events_filter, pump_filter},
+        pool::Pool,
+        pump_config::PumpConfig,
+        tx::{PendingTx, TxManager},
+    },
     contract::{
-        coin::{get_coin_calldata, get_graduated_calldata},
+        coin::{get_coin_calldata, get_decimals_calldata, get_graduated_calldata},
         factory::get_pair_calldata,
-        pair::{get_token0_calldata, get_token1_calldata},
-        pump::deploy_graduated_bytecode,
+        pair::{get_token0_calldata, get_token1_calldata, get_total_supply_calldata},
+        pump::{deploy_graduated_bytecode, get_coin_pair_calldata},
         SolidityCoin,
     },
     error::PumpError,
@@ -30,9 +34,8 @@ pub fn build_tx(to: &Address, calldata: Vec<u8>) -> TransactionRequest {
 pub struct PumpClient {
     pub chain_id: u64,
     provider: SeismicUnsignedProvider,
-    signer_address: Address,
-    wallet: EthereumWallet,
     pub ca: ContractAddresses,
+    pub config: PumpConfig,
 }
 
 impl PumpClient {
@@ -42,20 +45,16 @@ impl PumpClient {
 
         let chain_id = provider.get_chain_id().await?;
 
-        let private_key =
-            std::env::var("DEPLOYER_PRIVATE_KEY").expect("Missing DEPLOYER_PRIVATE_KEY in .env");
-        let pk_bytes = B256::from_hex(private_key).unwrap();
-        let signer = LocalSigner::from_bytes(&pk_bytes).expect("invalid signer");
-        let signer_address = signer.address();
-        let wallet = EthereumWallet::new(signer);
-
-        Ok(PumpClient {
-            provider,
-            signer_address,
-            wallet,
-            ca: ContractAddresses::new(chain_id),
+        let ca = ContractAddresses::new(chain_id);
+        let config = PumpConfig::load(&provider, chain_id, ca.pump).await?;
+        log::info!(
+            "PumpRand on chain {} graduates coins at {} wei with a {} bps fee",
             chain_id,
-        })
+            config.wei_graduation,
+            config.fee_bps
+        );
+
+        Ok(PumpClient { provider, ca, config, chain_id })
     }
 
     pub async fn get_chain_id(&self) -> Result<u64, PumpError> {
@@ -95,7 +94,17 @@ impl PumpClient {
         let token_1 = ContractAddresses::get_address(&self.provider, &lp_token, token1_calldata)
             .await
             .map_err(|_| PumpError::PairNotFound(lp_token.clone()))?;
-        Ok(Pool { lp_token, token_0, token_1 })
+        let decimals_0 = self.get_decimals(token_0).await?;
+        let decimals_1 = self.get_decimals(token_1).await?;
+        Ok(Pool { lp_token, token_0, token_1, decimals_0, decimals_1 })
+    }
+
+    pub async fn get_decimals(&self, token: Address) -> Result<u8, PumpError> {
+        let tx = build_tx(&token, get_decimals_calldata());
+        let bytes = self.provider.call(&tx).await?;
+        let decimals =
+            Uint::<8>::abi_decode(&bytes, true).map_err(|_| PumpError::FailedToDecodeAbi)?;
+        Ok(decimals)
     }
 
     pub fn weth(&self) -> Address {
@@ -106,21 +115,85 @@ impl PumpClient {
         self.ca.router
     }
 
-    pub async fn deploy_graduated(&self, coin_id: u32) -> Result<FixedBytes<32>, TransportError> {
+    fn deploy_graduated_tx(&self, coin_id: u32) -> TransactionRequest {
         let input = deploy_graduated_bytecode(coin_id);
-        let tx = TransactionRequest::default()
-            .to(self.ca.pump)
-            .input(input.into())
-            .from(self.signer_address);
-        let pending_tx = {
-            // drop provider so we don't constantly ask the node for block number
-            let provider = SeismicSignedProvider::new(
-                self.wallet.clone(),
-                Url::from_str(self.provider.client().transport().url()).unwrap(),
-            );
-            provider.send_transaction(tx).await?
-        };
-        Ok(pending_tx.tx_hash().clone())
+        TransactionRequest::default().to(self.ca.pump).input(input.into())
+    }
+
+    /// Sends deployGraduated from the deployer without waiting for it to be mined
+    pub async fn send_deploy_graduated(
+        &self,
+        txs: &TxManager,
+        coin_id: u32,
+    ) -> Result<PendingTx, PumpError> {
+        txs.send(self.deploy_graduated_tx(coin_id)).await
+    }
+
+    /// Replaces a stuck deployGraduated tx with one paying higher fees
+    pub async fn replace_deploy_graduated(
+        &self,
+        txs: &TxManager,
+        coin_id: u32,
+        stuck: &PendingTx,
+    ) -> Result<PendingTx, PumpError> {
+        txs.replace(stuck, self.deploy_graduated_tx(coin_id)).await
+    }
+
+    /// The coin's dex pair if it has liquidity, i.e. the coin was already deployed
+    pub async fn get_deployed_pair(&self, coin_id: u32) -> Result<Option<Address>, PumpError> {
+        let tx = build_tx(&self.ca.pump, get_coin_pair_calldata(coin_id));
+        let bytes = self.provider.call(&tx).await?;
+        let pair =
+            SolAddress::abi_decode(&bytes, true).map_err(|_| PumpError::FailedToDecodeAbi)?;
+        if pair == Address::ZERO {
+            return Ok(None);
+        }
+        // anyone can create the pair, so only count it once it has LP tokens
+        let tx = build_tx(&pair, get_total_supply_calldata());
+        let bytes = self.provider.call(&tx).await?;
+        let supply =
+            Uint::<256>::abi_decode(&bytes, true).map_err(|_| PumpError::FailedToDecodeAbi)?;
+        Ok((!supply.is_zero()).then_some(pair))
+    }
+
+    pub async fn get_block_number(&self) -> Result<u64, PumpError> {
+        let block_number = self.provider.get_block_number().await?;
+        Ok(block_number)
+    }
+
+    /// Fetches every PumpRand log emitted in the inclusive block range
+    pub async fn get_pump_logs(
+        &self,
+        from_block: u64,
+        to_block: u64,
+    ) -> Result<Vec<Log>, PumpError> {
+        let filter = pump_filter(self.ca.pump).from_block(from_block).to_block(to_block);
+        let logs = self.provider.get_logs(&filter).await?;
+        Ok(logs)
+    }
+
+    /// Fetches Swap and Sync logs from every pair in the inclusive block range
+    pub async fn get_pair_logs(
+        &self,
+        from_block: u64,
+        to_block: u64,
+    ) -> Result<Vec<Log>, PumpError> {
+        let filter = pair_events_filter().from_block(from_block).to_block(to_block);
+        let logs = self.provider.get_logs(&filter).await?;
+        Ok(logs)
+    }
+
+    /// Returns the number of the block the node reports for a tag like `safe` or `finalized`,
+    /// or None if the node doesn't track it
+    pub async fn get_tagged_block_number(
+        &self,
+        tag: BlockNumberOrTag,
+    ) -> Result<Option<u64>, PumpError> {
+        let block = self
+            .provider
+            .get_block_by_number(tag, alloy_rpc_types_eth::BlockTransactionsKind::Hashes)
+            .await?;
+        Ok(block.map(|block| block.header.number))
     }
 
     pub async fn get_block_header(&self, block_number: u64) -> Result<Header, PumpError> {
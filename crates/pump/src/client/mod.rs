@@ -4,12 +4,16 @@ use alloy_rpc_types_eth::{TransactionInput, TransactionRequest};
 
 pub mod block;
 mod contract_address;
+pub mod filter;
 pub mod pool;
+mod pump_config;
+pub mod tx;
 
 mod http;
 mod ws;
 
 pub use http::PumpClient;
+pub use pump_config::PumpConfig;
 pub use ws::PumpWsClient;
 
 pub fn build_tx(to: &Address, calldata: Vec<u8>) -> TransactionRequest {
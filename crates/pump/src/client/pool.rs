@@ -3,6 +3,7 @@ use bigdecimal::{
     num_bigint::{BigInt, Sign},
     BigDecimal, One, Zero,
 };
+use serde::{Deserialize, Serialize};
 use std::str::FromStr;
 
 use crate::{contract::pair::UniswapV2Pair, db::models, error::PumpError};
@@ -12,6 +13,16 @@ pub struct Pool {
     pub lp_token: Address,
     pub token_0: Address,
     pub token_1: Address,
+    pub decimals_0: u8,
+    pub decimals_1: u8,
+}
+
+/// Whether a trader bought or sold the pool's coin
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TradeSide {
+    Buy,
+    Sell,
 }
 
 impl Pool {
@@ -35,6 +46,8 @@ impl TryFrom<models::Pool> for Pool {
             lp_token: parse_address(&value.address)?,
             token_0: parse_address(&value.token_0)?,
             token_1: parse_address(&value.token_1)?,
+            decimals_0: value.decimals_0 as u8,
+            decimals_1: value.decimals_1 as u8,
         })
     }
 }
@@ -47,8 +60,13 @@ fn to_bigint<const BITS: usize, const LIMBS: usize>(i: Uint<BITS, LIMBS>) -> Big
     BigInt::from_bytes_le(Sign::Plus, i.to_le_bytes_vec().as_ref())
 }
 
+/// 10^decimals, one whole token in the token's smallest unit
+fn unit(decimals: u8) -> BigDecimal {
+    BigDecimal::from(BigInt::from(10u8).pow(decimals as u32))
+}
+
 impl Pool {
-    // TODO: add decimals when we need them (all coins have 18 now)
+    /// Amounts and price are in each token's smallest unit, see `to_ui_volume` and `to_ui_price`
     pub fn swap_price(
         swap: &UniswapV2Pair::Swap,
     ) -> Option<(bool, BigDecimal, BigDecimal, BigDecimal)> {
@@ -77,7 +95,32 @@ impl Pool {
         amt_1 / amt_0
     }
 
+    /// Splits a swap's amounts into (coin, ETH) volume in whole tokens
+    pub fn to_ui_volume(
+        &self,
+        weth: Address,
+        amount_0: &BigDecimal,
+        amount_1: &BigDecimal,
+    ) -> (BigDecimal, BigDecimal) {
+        let amount_0 = amount_0 / unit(self.decimals_0);
+        let amount_1 = amount_1 / unit(self.decimals_1);
+        match self.token_0 == weth {
+            true => (amount_1, amount_0),
+            false => (amount_0, amount_1),
+        }
+    }
+
+    /// `buy_0` is true when the trader received token 0
+    pub fn trade_side(&self, weth: Address, buy_0: bool) -> TradeSide {
+        match buy_0 != (self.token_0 == weth) {
+            true => TradeSide::Buy,
+            false => TradeSide::Sell,
+        }
+    }
+
     pub fn to_ui_price(&self, weth: Address, dex_price: BigDecimal) -> BigDecimal {
+        // whole token 1 per whole token 0
+        let dex_price = dex_price * unit(self.decimals_0) / unit(self.decimals_1);
         if self.token_0 == weth {
             // flip the price if the first token is weth,
             // since we always want to display how much ETH a token is worth
@@ -86,3 +129,42 @@ impl Pool {
         dex_price
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WETH: Address = Address::repeat_byte(0xee);
+    const COIN: Address = Address::repeat_byte(0xc0);
+
+    /// A pool whose coin has 6 decimals
+    fn pool(weth_first: bool) -> Pool {
+        let (token_0, token_1, decimals_0, decimals_1) = match weth_first {
+            true => (WETH, COIN, 18, 6),
+            false => (COIN, WETH, 6, 18),
+        };
+        Pool { lp_token: Address::repeat_byte(1), token_0, token_1, decimals_0, decimals_1 }
+    }
+
+    fn decimal(s: &str) -> BigDecimal {
+        BigDecimal::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn test_to_ui_volume() {
+        // 2 coins for half an ETH
+        let coin = decimal("2000000");
+        let eth = decimal("500000000000000000");
+        assert_eq!(pool(false).to_ui_volume(WETH, &coin, &eth), (decimal("2"), decimal("0.5")));
+        assert_eq!(pool(true).to_ui_volume(WETH, &eth, &coin), (decimal("2"), decimal("0.5")));
+    }
+
+    #[test]
+    fn test_to_ui_price() {
+        // a quarter ETH per coin, in each token's smallest unit
+        let wei_per_unit = decimal("250000000000");
+        assert_eq!(pool(false).to_ui_price(WETH, wei_per_unit.clone()), decimal("0.25"));
+        let units_per_wei = BigDecimal::one() / wei_per_unit;
+        assert_eq!(pool(true).to_ui_price(WETH, units_per_wei), decimal("0.25"));
+    }
+}
@@ -0,0 +1,71 @@
+use alloy_primitives::{Address, U256};
+use alloy_provider::{Provider, SeismicUnsignedProvider};
+use alloy_sol_types::{sol_data::Uint, SolType};
+use bigdecimal::BigDecimal;
+use serde::Serialize;
+
+use crate::{
+    client::{build_tx, pool::int_to_decimal},
+    contract::pump::{fee_bps_calldata, wei_graduation_calldata},
+    error::PumpError,
+};
+
+/// PumpRand's bonding curve parameters, read from the deployed contract
+/// so every service agrees with it even if a new deployment changes them
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PumpConfig {
+    pub chain_id: u64,
+    pub pump: Address,
+    /// wei a coin must raise on its bonding curve before it graduates
+    pub wei_graduation: BigDecimal,
+    /// fee taken on each purchase, in basis points
+    pub fee_bps: u16,
+}
+
+impl PumpConfig {
+    pub(crate) async fn load(
+        provider: &SeismicUnsignedProvider,
+        chain_id: u64,
+        pump: Address,
+    ) -> Result<PumpConfig, PumpError> {
+        let tx = build_tx(&pump, wei_graduation_calldata());
+        let bytes = provider.call(&tx).await?;
+        let wei_graduation =
+            Uint::<256>::abi_decode(&bytes, true).map_err(|_| PumpError::FailedToDecodeAbi)?;
+
+        let tx = build_tx(&pump, fee_bps_calldata());
+        let fee_bps = match provider.call(&tx).await {
+            Ok(bytes) => {
+                Uint::<16>::abi_decode(&bytes, true).map_err(|_| PumpError::FailedToDecodeAbi)?
+            }
+            Err(e) => {
+                // deployments from before feeBps was public have no getter
+                log::warn!("PumpRand has no feeBps getter, reading its storage: {:?}", e);
+                let slot = provider.get_storage_at(pump, U256::ZERO).await?;
+                fee_bps_from_slot(slot)
+            }
+        };
+
+        Ok(PumpConfig { chain_id, pump, wei_graduation: int_to_decimal(wei_graduation), fee_bps })
+    }
+}
+
+/// feeBps is PumpRand's first variable in storage, so it's packed
+/// into the low 2 bytes of slot 0 alongside coinsCreated and deployer
+fn fee_bps_from_slot(slot: U256) -> u16 {
+    (slot & U256::from(u16::MAX)).to::<u16>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fee_bps_from_slot() {
+        let deployer = U256::from_be_slice(Address::repeat_byte(0xff).as_slice());
+        let coins_created = U256::from(u32::MAX);
+        let slot = (deployer << 48) | (coins_created << 16) | U256::from(250);
+        assert_eq!(fee_bps_from_slot(slot), 250);
+    }
+}
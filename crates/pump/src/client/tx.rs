@@ -0,0 +1,200 @@
+use alloy_network::{EthereumWallet, ReceiptResponse, TransactionBuilder};
+use alloy_primitives::{hex::FromHex, Address, B256};
+use alloy_provider::{Provider, SeismicSignedProvider, SeismicUnsignedProvider};
+use alloy_rpc_types_eth::TransactionRequest;
+use alloy_signer_local::LocalSigner;
+use reqwest::Url;
+use std::{fmt, str::FromStr};
+use tokio::sync::Mutex;
+
+use crate::error::PumpError;
+
+/// nodes reject replacements that don't raise fees by at least 10%
+const MIN_REPLACEMENT_BUMP_PERCENT: u64 = 10;
+
+fn env_u64(key: &str) -> Option<u64> {
+    std::env::var(key).ok().and_then(|value| value.parse::<u64>().ok())
+}
+
+#[derive(Debug, Clone)]
+pub struct TxConfig {
+    /// blocks a receipt must be under before it counts as confirmed
+    pub confirmations: u64,
+    /// how much a replacement raises fees, in percent
+    pub fee_bump_percent: u64,
+}
+
+impl TxConfig {
+    /// Reads `TX_CONFIRMATIONS` and `TX_FEE_BUMP_PERCENT`
+    pub fn from_env() -> TxConfig {
+        TxConfig {
+            confirmations: env_u64("TX_CONFIRMATIONS").unwrap_or(1).max(1),
+            fee_bump_percent: env_u64("TX_FEE_BUMP_PERCENT")
+                .unwrap_or(20)
+                .max(MIN_REPLACEMENT_BUMP_PERCENT),
+        }
+    }
+}
+
+/// A tx we sent that may not be mined yet
+#[derive(Debug, Clone)]
+pub struct PendingTx {
+    pub hash: B256,
+    pub nonce: u64,
+    /// fees it was sent with, or 0 if unknown, e.g. for a job saved before we stored them
+    pub max_fee_per_gas: u128,
+    pub max_priority_fee_per_gas: u128,
+}
+
+#[derive(Debug, Clone)]
+pub struct TxReceipt {
+    pub hash: B256,
+    pub block_number: Option<u64>,
+    pub gas_used: u64,
+    pub effective_gas_price: u128,
+}
+
+#[derive(Debug, Clone)]
+pub enum TxOutcome {
+    /// succeeded and is `confirmations` blocks deep
+    Confirmed(TxReceipt),
+    Reverted(TxReceipt),
+    /// succeeded, but isn't deep enough to count as confirmed
+    Mined(TxReceipt),
+    /// not mined yet
+    Pending(PendingTx),
+}
+
+/// Sends the deployer's txs. Owns its nonce so concurrent sends never collide,
+/// and can replace txs that get stuck in the mempool.
+/// Build one per deployer and share it, or nonces will collide
+pub struct TxManager {
+    provider: SeismicUnsignedProvider,
+    signer: SeismicSignedProvider,
+    address: Address,
+    /// the signer's next nonce, or None to ask the node. Held while sending
+    next_nonce: Mutex<Option<u64>>,
+    config: TxConfig,
+}
+
+impl fmt::Debug for TxManager {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TxManager")
+            .field("address", &self.address)
+            .field("config", &self.config)
+            .finish_non_exhaustive()
+    }
+}
+
+impl TxManager {
+    /// Signs with `DEPLOYER_PRIVATE_KEY`
+    pub fn from_env(rpc_url: &str) -> TxManager {
+        let rpc_url = Url::from_str(rpc_url).expect("Missing RPC_URL in .env");
+        let private_key =
+            std::env::var("DEPLOYER_PRIVATE_KEY").expect("Missing DEPLOYER_PRIVATE_KEY in .env");
+        let pk_bytes = B256::from_hex(private_key).unwrap();
+        let signer = LocalSigner::from_bytes(&pk_bytes).expect("invalid signer");
+        let address = signer.address();
+        TxManager::new(rpc_url, EthereumWallet::new(signer), address)
+    }
+
+    fn new(rpc_url: Url, wallet: EthereumWallet, address: Address) -> TxManager {
+        TxManager {
+            provider: SeismicUnsignedProvider::new(rpc_url.clone()),
+            signer: SeismicSignedProvider::new(wallet, rpc_url),
+            address,
+            next_nonce: Mutex::new(None),
+            config: TxConfig::from_env(),
+        }
+    }
+
+    pub fn address(&self) -> Address {
+        self.address
+    }
+
+    /// Sends the tx with the signer's next nonce
+    pub async fn send(&self, tx: TransactionRequest) -> Result<PendingTx, PumpError> {
+        let mut next_nonce = self.next_nonce.lock().await;
+        let nonce = match *next_nonce {
+            Some(nonce) => nonce,
+            None => self.provider.get_transaction_count(self.address).pending().await?,
+        };
+        match self.sign_and_send(tx, nonce, None).await {
+            Ok(pending) => {
+                *next_nonce = Some(nonce + 1);
+                Ok(pending)
+            }
+            Err(e) => {
+                // we can't tell if the node took the nonce, so ask it next time
+                *next_nonce = None;
+                Err(e)
+            }
+        }
+    }
+
+    /// Re-sends a stuck tx with the same nonce and higher fees
+    pub async fn replace(
+        &self,
+        stuck: &PendingTx,
+        tx: TransactionRequest,
+    ) -> Result<PendingTx, PumpError> {
+        let _next_nonce = self.next_nonce.lock().await;
+        self.sign_and_send(tx, stuck.nonce, Some(stuck)).await
+    }
+
+    async fn sign_and_send(
+        &self,
+        tx: TransactionRequest,
+        nonce: u64,
+        replacing: Option<&PendingTx>,
+    ) -> Result<PendingTx, PumpError> {
+        let estimate = self.provider.estimate_eip1559_fees(None).await?;
+        let (max_fee_per_gas, max_priority_fee_per_gas) = match replacing {
+            Some(stuck) => {
+                let bump = |estimate: u128, previous: u128| {
+                    estimate
+                        .max(previous)
+                        .saturating_mul(100 + self.config.fee_bump_percent as u128)
+                        / 100
+                };
+                (
+                    bump(estimate.max_fee_per_gas, stuck.max_fee_per_gas),
+                    bump(estimate.max_priority_fee_per_gas, stuck.max_priority_fee_per_gas),
+                )
+            }
+            None => (estimate.max_fee_per_gas, estimate.max_priority_fee_per_gas),
+        };
+        let tx = tx
+            .from(self.address)
+            .nonce(nonce)
+            .max_fee_per_gas(max_fee_per_gas)
+            .max_priority_fee_per_gas(max_priority_fee_per_gas);
+        let gas_limit = self.provider.estimate_gas(&tx).await?;
+        let sent = self.signer.send_transaction(tx.gas_limit(gas_limit)).await?;
+        Ok(PendingTx { hash: *sent.tx_hash(), nonce, max_fee_per_gas, max_priority_fee_per_gas })
+    }
+
+    /// Checks on the tx once
+    pub async fn poll(&self, pending: &PendingTx) -> Result<TxOutcome, PumpError> {
+        let receipt = match self.provider.get_transaction_receipt(pending.hash).await? {
+            Some(receipt) => receipt,
+            None => return Ok(TxOutcome::Pending(pending.clone())),
+        };
+        let tx_receipt = TxReceipt {
+            hash: receipt.transaction_hash(),
+            block_number: receipt.block_number(),
+            gas_used: receipt.gas_used() as u64,
+            effective_gas_price: receipt.effective_gas_price(),
+        };
+        if !receipt.status() {
+            return Ok(TxOutcome::Reverted(tx_receipt));
+        }
+        if let Some(mined) = tx_receipt.block_number {
+            let head = self.provider.get_block_number().await?;
+            if head + 1 < mined + self.config.confirmations {
+                return Ok(TxOutcome::Mined(tx_receipt));
+            }
+        }
+        Ok(TxOutcome::Confirmed(tx_receipt))
+    }
+}
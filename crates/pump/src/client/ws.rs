@@ -1,41 +1,68 @@
 use crate::{
-    client::contract_address::ContractAddresses, contract::pair::UniswapV2Pair, error::PumpError,
+    client::{
+        contract_address::ContractAddresses,
+        filter::{pair_events_filter, pump_filter},
+    },
+    error::PumpError,
 };
-use alloy_primitives::{Address, FixedBytes};
+use alloy_primitives::FixedBytes;
 use alloy_provider::{Provider, SeismicUnsignedWsProvider};
 use alloy_pubsub::SubscriptionStream;
-use alloy_rpc_types_eth::{Filter, Header, Log};
-use alloy_sol_types::SolEvent;
+use alloy_rpc_types_eth::{Header, Log};
 use alloy_transport::TransportError;
+use std::time::Duration;
+
+const RECONNECT_BASE_DELAY: Duration = Duration::from_millis(500);
+const RECONNECT_MAX_DELAY: Duration = Duration::from_secs(30);
 
 #[derive(Debug, Clone)]
 pub struct PumpWsClient {
     pub ws: SeismicUnsignedWsProvider,
+    ws_url: String,
     ca: ContractAddresses,
 }
 
 impl PumpWsClient {
     pub async fn new(chain_id: u64) -> Result<PumpWsClient, TransportError> {
         let ws_url = std::env::var("WS_RPC_URL").expect("Missing WS_RPC_URL in .env");
-        let ws = SeismicUnsignedWsProvider::new(ws_url).await?;
-        Ok(PumpWsClient { ws, ca: ContractAddresses::new(chain_id) })
+        let ws = SeismicUnsignedWsProvider::new(ws_url.clone()).await?;
+        Ok(PumpWsClient { ws, ws_url, ca: ContractAddresses::new(chain_id) })
+    }
+
+    /// Replaces a dropped websocket transport, retrying with exponential backoff.
+    /// Existing subscriptions die with the old transport, so callers must resubscribe
+    pub async fn reconnect(&mut self) {
+        let mut delay = RECONNECT_BASE_DELAY;
+        loop {
+            match SeismicUnsignedWsProvider::new(self.ws_url.clone()).await {
+                Ok(ws) => {
+                    log::info!("Reconnected to {}", self.ws_url);
+                    self.ws = ws;
+                    return;
+                }
+                Err(e) => {
+                    log::warn!(
+                        "Failed to reconnect to {}, retrying in {:?}: {:?}",
+                        self.ws_url,
+                        delay,
+                        e
+                    );
+                    tokio::time::sleep(delay).await;
+                    delay = (delay * 2).min(RECONNECT_MAX_DELAY);
+                }
+            }
+        }
     }
 
     pub async fn pump_logs(&self) -> Result<SubscriptionStream<Log>, PumpError> {
-        let pump_filter = Filter::new().address(self.ca.pump);
-        let sub = self.ws.inner().subscribe_logs(&pump_filter).await?;
+        let sub = self.ws.inner().subscribe_logs(&pump_filter(self.ca.pump)).await?;
         Ok(sub.into_stream())
     }
 
-    pub async fn pair_logs(
-        &self,
-        addresses: Vec<Address>,
-    ) -> Result<SubscriptionStream<Log>, PumpError> {
-        let filter = Filter::new().address(addresses).event_signature(vec![
-            UniswapV2Pair::Swap::SIGNATURE_HASH,
-            UniswapV2Pair::Sync::SIGNATURE_HASH,
-        ]);
-        let sub = self.ws.inner().subscribe_logs(&filter).await?;
+    /// Subscribes to Swap and Sync events from every pair by topic,
+    /// so pools deployed later are covered without resubscribing
+    pub async fn pair_logs(&self) -> Result<SubscriptionStream<Log>, PumpError> {
+        let sub = self.ws.inner().subscribe_logs(&pair_events_filter()).await?;
         Ok(sub.into_stream())
     }
 
@@ -13,6 +13,7 @@ sol! {
 
     mapping(uint32 => bool) public graduated;
     function getCoin(uint32 coinId) public view returns (SolidityCoin memory);
+    function decimals() public view returns (uint8);
 }
 
 pub fn get_coin_calldata(coin_id: u32) -> Vec<u8> {
@@ -23,6 +24,11 @@ pub fn get_graduated_calldata(coin_id: u32) -> Vec<u8> {
     graduatedCall { _0: coin_id }.abi_encode()
 }
 
+/// Works on any ERC20, including WETH
+pub fn get_decimals_calldata() -> Vec<u8> {
+    decimalsCall {}.abi_encode()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
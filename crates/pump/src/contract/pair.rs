@@ -1,10 +1,11 @@
 use alloy_sol_types::{sol, SolCall};
-use UniswapV2Pair::{token0Call, token1Call};
+use UniswapV2Pair::{token0Call, token1Call, totalSupplyCall};
 
 sol! {
     contract UniswapV2Pair {
         address public token0;
         address public token1;
+        uint public totalSupply;
 
         #[derive(Debug)]
         event Swap(
@@ -27,3 +28,7 @@ pub(crate) fn get_token0_calldata() -> Vec<u8> {
 pub(crate) fn get_token1_calldata() -> Vec<u8> {
     token1Call {}.abi_encode()
 }
+
+pub(crate) fn get_total_supply_calldata() -> Vec<u8> {
+    totalSupplyCall {}.abi_encode()
+}
@@ -1,5 +1,5 @@
 use alloy_sol_types::{sol, SolCall};
-use PumpRand::deployGraduatedCall;
+use PumpRand::{deployGraduatedCall, feeBpsCall, getPairCall, WEI_GRADUATIONCall};
 
 sol! {
     contract PumpRand {
@@ -13,9 +13,24 @@ sol! {
         event DeployedToDex(uint32 coinId, address lpToken);
 
         function deployGraduated(uint32 coinId) public;
+        function WEI_GRADUATION() public view returns (uint256);
+        function feeBps() public view returns (uint16);
+        function getPair(uint32 coinId) public view returns (address);
     }
 }
 
 pub(crate) fn deploy_graduated_bytecode(coin_id: u32) -> Vec<u8> {
     deployGraduatedCall { coinId: coin_id }.abi_encode()
 }
+
+pub(crate) fn wei_graduation_calldata() -> Vec<u8> {
+    WEI_GRADUATIONCall {}.abi_encode()
+}
+
+pub(crate) fn fee_bps_calldata() -> Vec<u8> {
+    feeBpsCall {}.abi_encode()
+}
+
+pub(crate) fn get_coin_pair_calldata(coin_id: u32) -> Vec<u8> {
+    getPairCall { coinId: coin_id }.abi_encode()
+}
@@ -1,14 +1,21 @@
 // src/models.rs
 
-use alloy_primitives::Address;
+use alloy_primitives::{keccak256, Address};
 use bigdecimal::{BigDecimal, ToPrimitive, Zero};
 use chrono::NaiveDateTime;
 use diesel::{prelude::*, sql_types::Numeric, Queryable};
 use serde::{Deserialize, Serialize};
 
-use crate::{client::block::Block, db::schema, error::PumpError};
+use crate::{
+    client::{
+        block::Block,
+        pool::{self, TradeSide},
+    },
+    db::schema,
+    error::PumpError,
+};
 
-#[derive(Queryable, Serialize, Deserialize, Debug, QueryableByName)]
+#[derive(Queryable, Serialize, Deserialize, Debug, Clone, QueryableByName)]
 #[diesel(table_name = schema::coins)]
 pub struct Coin {
     pub id: i64,
@@ -68,9 +75,13 @@ pub struct Pool {
     pub token_1: String,
     #[serde(rename = "createdAt")]
     pub created_at: NaiveDateTime,
+    #[serde(rename = "decimalsA")]
+    pub decimals_0: i32,
+    #[serde(rename = "decimalsB")]
+    pub decimals_1: i32,
 }
 
-#[derive(Insertable)]
+#[derive(Insertable, Clone)]
 #[diesel(table_name = schema::pool_prices)]
 pub struct NewPoolPrice {
     pub pool: String,
@@ -79,6 +90,25 @@ pub struct NewPoolPrice {
     pub high: BigDecimal,
     pub low: BigDecimal,
     pub close: BigDecimal,
+    pub volume_base: BigDecimal,
+    pub volume_quote: BigDecimal,
+    pub trade_count: i32,
+}
+
+/// Volume traded in a pool within a block, in whole tokens
+#[derive(Debug, Clone, Default)]
+pub struct CandleVolume {
+    pub base: BigDecimal,
+    pub quote: BigDecimal,
+    pub trades: i32,
+}
+
+impl CandleVolume {
+    pub fn add_trade(&mut self, base: BigDecimal, quote: BigDecimal) {
+        self.base += base;
+        self.quote += quote;
+        self.trades += 1;
+    }
 }
 
 impl NewPoolPrice {
@@ -86,6 +116,7 @@ impl NewPoolPrice {
         lp_token: &Address,
         block: Block,
         prices: &Vec<BigDecimal>,
+        volume: CandleVolume,
     ) -> Result<NewPoolPrice, PumpError> {
         if prices.is_empty() {
             return Err(PumpError::no_prices(block.number, lp_token.clone()));
@@ -101,19 +132,22 @@ impl NewPoolPrice {
             high,
             low,
             close,
+            volume_base: volume.base,
+            volume_quote: volume.quote,
+            trade_count: volume.trades,
         };
         Ok(price)
     }
 }
 
-fn serialize_decimal_as_f64<S>(decimal: &BigDecimal, serializer: S) -> Result<S::Ok, S::Error>
+pub fn serialize_decimal_as_f64<S>(decimal: &BigDecimal, serializer: S) -> Result<S::Ok, S::Error>
 where
     S: serde::Serializer,
 {
     serializer.serialize_f64(decimal.to_f64().unwrap_or(0.0))
 }
 
-#[derive(Serialize, Queryable, Selectable)]
+#[derive(Serialize, Deserialize, Queryable, Selectable, QueryableByName, Clone, Debug)]
 #[diesel(table_name = schema::pool_prices)]
 pub struct PoolPriceData {
     pub time: i64,
@@ -125,9 +159,48 @@ pub struct PoolPriceData {
     pub low: BigDecimal,
     #[serde(serialize_with = "serialize_decimal_as_f64")]
     pub close: BigDecimal,
+    #[serde(rename = "volumeBase", serialize_with = "serialize_decimal_as_f64")]
+    pub volume_base: BigDecimal,
+    #[serde(rename = "volumeQuote", serialize_with = "serialize_decimal_as_f64")]
+    pub volume_quote: BigDecimal,
+    #[serde(rename = "tradeCount")]
+    pub trade_count: i32,
+}
+
+impl From<NewPoolPrice> for PoolPriceData {
+    fn from(price: NewPoolPrice) -> Self {
+        PoolPriceData {
+            time: price.time,
+            open: price.open,
+            high: price.high,
+            low: price.low,
+            close: price.close,
+            volume_base: price.volume_base,
+            volume_quote: price.volume_quote,
+            trade_count: price.trade_count,
+        }
+    }
+}
+
+impl PoolPriceData {
+    /// A candle for a bucket without trades, flat at the previous close
+    pub fn flat(time: i64, close: BigDecimal) -> PoolPriceData {
+        PoolPriceData {
+            time,
+            open: close.clone(),
+            high: close.clone(),
+            low: close.clone(),
+            close,
+            volume_base: BigDecimal::zero(),
+            volume_quote: BigDecimal::zero(),
+            trade_count: 0,
+        }
+    }
 }
 
-#[derive(Queryable, Insertable)]
+/// A trade to insert. Read trades back as `TradeRow`,
+/// since rows from before log indexes were recorded have none
+#[derive(Insertable)]
 #[diesel(table_name = schema::trades)]
 pub struct Trade {
     pub tx: String,
@@ -137,6 +210,272 @@ pub struct Trade {
     pub amount_0: BigDecimal,
     pub amount_1: BigDecimal,
     pub time: i64,
+    pub log_index: i64,
+    pub block_number: Option<i64>,
+}
+
+#[derive(Queryable, Selectable, Debug)]
+#[diesel(table_name = schema::trades)]
+pub struct TradeRow {
+    pub id: i64,
+    pub tx: String,
+    pub pool: String,
+    pub trader: String,
+    pub buy_0: bool,
+    pub amount_0: BigDecimal,
+    pub amount_1: BigDecimal,
+    pub time: i64,
+    pub block_number: Option<i64>,
+}
+
+/// A trade as the API returns it, relative to the pool's coin
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TradeData {
+    pub id: i64,
+    pub tx: String,
+    pub pool: String,
+    pub trader: String,
+    /// the coin's address
+    pub token: String,
+    pub side: TradeSide,
+    #[serde(serialize_with = "serialize_decimal_as_f64")]
+    pub amount_token: BigDecimal,
+    #[serde(serialize_with = "serialize_decimal_as_f64")]
+    pub amount_eth: BigDecimal,
+    /// ETH per coin
+    #[serde(serialize_with = "serialize_decimal_as_f64")]
+    pub price: BigDecimal,
+    pub time: i64,
+    pub block_number: Option<i64>,
+}
+
+impl TradeData {
+    pub fn new(row: TradeRow, pool: &pool::Pool, weth: Address) -> TradeData {
+        let (token, _) = pool.other(weth);
+        let (amount_token, amount_eth) = pool.to_ui_volume(weth, &row.amount_0, &row.amount_1);
+        let price = match amount_token.is_zero() {
+            true => BigDecimal::zero(),
+            false => &amount_eth / &amount_token,
+        };
+        TradeData {
+            id: row.id,
+            tx: row.tx,
+            pool: row.pool,
+            trader: row.trader,
+            token: token.to_string(),
+            side: pool.trade_side(weth, row.buy_0),
+            amount_token,
+            amount_eth,
+            price,
+            time: row.time,
+            block_number: row.block_number,
+        }
+    }
+}
+
+/// A purchase or refund on a coin's bonding curve, from its WeiInUpdated log
+#[derive(Insertable)]
+#[diesel(table_name = schema::wei_in_updates)]
+pub struct NewWeiInUpdate {
+    pub coin_id: i64,
+    pub block_number: i64,
+    pub log_index: i64,
+    pub tx: String,
+    pub time: i64,
+    pub wei_in: BigDecimal,
+    /// change in wei in: positive for buys, negative for refunds
+    pub delta: BigDecimal,
+}
+
+#[derive(Queryable, Selectable, Debug)]
+#[diesel(table_name = schema::wei_in_updates)]
+pub struct WeiInUpdateRow {
+    pub id: i64,
+    pub coin_id: i64,
+    pub block_number: i64,
+    pub log_index: i64,
+    pub tx: String,
+    pub time: i64,
+    pub wei_in: BigDecimal,
+    pub delta: BigDecimal,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum WeiInKind {
+    Buy,
+    Refund,
+}
+
+/// One step of a coin's progress toward graduation
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ProgressPoint {
+    pub block_number: i64,
+    pub tx: String,
+    pub time: i64,
+    pub kind: WeiInKind,
+    #[serde(serialize_with = "serialize_decimal_as_f64")]
+    pub wei_in: BigDecimal,
+    #[serde(serialize_with = "serialize_decimal_as_f64")]
+    pub delta: BigDecimal,
+}
+
+impl From<WeiInUpdateRow> for ProgressPoint {
+    fn from(row: WeiInUpdateRow) -> ProgressPoint {
+        let kind = match row.delta < BigDecimal::zero() {
+            true => WeiInKind::Refund,
+            false => WeiInKind::Buy,
+        };
+        ProgressPoint {
+            block_number: row.block_number,
+            tx: row.tx,
+            time: row.time,
+            kind,
+            wei_in: row.wei_in,
+            delta: row.delta,
+        }
+    }
+}
+
+/// Where a graduated coin is in being deployed to the dex
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeploymentStatus {
+    /// waiting to send deployGraduated
+    Pending,
+    /// deployGraduated was sent, waiting for its receipt
+    Submitted,
+    Deployed,
+    /// gave up after too many attempts
+    Failed,
+}
+
+impl DeploymentStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeploymentStatus::Pending => "pending",
+            DeploymentStatus::Submitted => "submitted",
+            DeploymentStatus::Deployed => "deployed",
+            DeploymentStatus::Failed => "failed",
+        }
+    }
+
+    fn parse(value: &str) -> Option<DeploymentStatus> {
+        match value {
+            "pending" => Some(DeploymentStatus::Pending),
+            "submitted" => Some(DeploymentStatus::Submitted),
+            "deployed" => Some(DeploymentStatus::Deployed),
+            "failed" => Some(DeploymentStatus::Failed),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Queryable, Selectable, Debug, Clone)]
+#[diesel(table_name = schema::deployment_jobs)]
+pub struct DeploymentJob {
+    pub coin_id: i64,
+    pub status: String,
+    pub tx: Option<String>,
+    pub nonce: Option<i64>,
+    pub attempts: i32,
+    pub last_error: Option<String>,
+    pub next_attempt_at: NaiveDateTime,
+    pub submitted_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+    pub max_fee_per_gas: Option<BigDecimal>,
+    pub max_priority_fee_per_gas: Option<BigDecimal>,
+}
+
+impl DeploymentJob {
+    pub fn status(&self) -> DeploymentStatus {
+        DeploymentStatus::parse(&self.status).unwrap_or(DeploymentStatus::Pending)
+    }
+}
+
+/// Columns to change on a deployment job. None leaves a column as it is
+#[derive(AsChangeset, Default)]
+#[diesel(table_name = schema::deployment_jobs)]
+pub struct DeploymentJobChanges {
+    pub status: Option<String>,
+    pub tx: Option<Option<String>>,
+    pub nonce: Option<Option<i64>>,
+    pub attempts: Option<i32>,
+    pub last_error: Option<Option<String>>,
+    pub next_attempt_at: Option<NaiveDateTime>,
+    pub submitted_at: Option<Option<NaiveDateTime>>,
+    pub max_fee_per_gas: Option<Option<BigDecimal>>,
+    pub max_priority_fee_per_gas: Option<Option<BigDecimal>>,
+}
+
+/// What an API key is allowed to do
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApiScope {
+    /// POST /coin/:id/verify
+    Verify,
+    /// POST /coin/:id/sync
+    Sync,
+    /// POST /coin/:id/deploy, which spends the deployer's gas
+    Deploy,
+    /// every scope
+    Admin,
+}
+
+impl ApiScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApiScope::Verify => "verify",
+            ApiScope::Sync => "sync",
+            ApiScope::Deploy => "deploy",
+            ApiScope::Admin => "admin",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<ApiScope> {
+        match value {
+            "verify" => Some(ApiScope::Verify),
+            "sync" => Some(ApiScope::Sync),
+            "deploy" => Some(ApiScope::Deploy),
+            "admin" => Some(ApiScope::Admin),
+            _ => None,
+        }
+    }
+}
+
+/// Keys are random, so a fast hash is enough to keep them out of the DB
+pub fn hash_api_key(key: &str) -> String {
+    keccak256(key.as_bytes()).to_string()
+}
+
+#[derive(Queryable, Selectable, Debug)]
+#[diesel(table_name = schema::api_keys)]
+pub struct ApiKey {
+    pub id: i64,
+    pub name: String,
+    pub key_hash: String,
+    pub scopes: Vec<String>,
+    pub created_at: NaiveDateTime,
+    pub last_used_at: Option<NaiveDateTime>,
+    pub revoked_at: Option<NaiveDateTime>,
+}
+
+impl ApiKey {
+    pub fn has_scope(&self, scope: ApiScope) -> bool {
+        self.scopes
+            .iter()
+            .filter_map(|s| ApiScope::parse(s))
+            .any(|s| s == scope || s == ApiScope::Admin)
+    }
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = schema::api_keys)]
+pub struct NewApiKey {
+    pub name: String,
+    pub key_hash: String,
+    pub scopes: Vec<String>,
 }
 
 #[derive(QueryableByName, Serialize, Debug)]
@@ -1,5 +1,26 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    api_keys (id) {
+        id -> Int8,
+        name -> Text,
+        #[max_length = 66]
+        key_hash -> Bpchar,
+        scopes -> Array<Text>,
+        created_at -> Timestamp,
+        last_used_at -> Nullable<Timestamp>,
+        revoked_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    block_cursors (chain_id) {
+        chain_id -> Int4,
+        block_number -> Int8,
+        updated_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     coins (id) {
         id -> Int8,
@@ -26,6 +47,33 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    deployment_jobs (coin_id) {
+        coin_id -> Int8,
+        #[max_length = 16]
+        status -> Varchar,
+        #[max_length = 66]
+        tx -> Nullable<Varchar>,
+        nonce -> Nullable<Int8>,
+        attempts -> Int4,
+        last_error -> Nullable<Text>,
+        next_attempt_at -> Timestamp,
+        submitted_at -> Nullable<Timestamp>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+        max_fee_per_gas -> Nullable<Numeric>,
+        max_priority_fee_per_gas -> Nullable<Numeric>,
+    }
+}
+
+diesel::table! {
+    notify_payloads (id) {
+        id -> Int8,
+        payload -> Text,
+        created_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     pool_prices (id) {
         id -> Int8,
@@ -36,6 +84,9 @@ diesel::table! {
         high -> Numeric,
         low -> Numeric,
         close -> Numeric,
+        volume_base -> Numeric,
+        volume_quote -> Numeric,
+        trade_count -> Int4,
     }
 }
 
@@ -51,6 +102,19 @@ diesel::table! {
         #[max_length = 42]
         token_1 -> Bpchar,
         created_at -> Timestamp,
+        decimals_0 -> Int4,
+        decimals_1 -> Int4,
+    }
+}
+
+diesel::table! {
+    signature_nonces (signer, nonce) {
+        #[max_length = 42]
+        signer -> Bpchar,
+        #[max_length = 128]
+        nonce -> Varchar,
+        expires_at -> Timestamp,
+        created_at -> Timestamp,
     }
 }
 
@@ -67,10 +131,39 @@ diesel::table! {
         amount_0 -> Numeric,
         amount_1 -> Numeric,
         time -> Int8,
+        log_index -> Nullable<Int8>,
+        block_number -> Nullable<Int8>,
+    }
+}
+
+diesel::table! {
+    wei_in_updates (id) {
+        id -> Int8,
+        coin_id -> Int8,
+        block_number -> Int8,
+        log_index -> Int8,
+        #[max_length = 66]
+        tx -> Varchar,
+        time -> Int8,
+        wei_in -> Numeric,
+        delta -> Numeric,
     }
 }
 
 diesel::joinable!(coins -> pools (deployed_pool));
+diesel::joinable!(deployment_jobs -> coins (coin_id));
 diesel::joinable!(pool_prices -> pools (pool));
+diesel::joinable!(wei_in_updates -> coins (coin_id));
 
-diesel::allow_tables_to_appear_in_same_query!(coins, pool_prices, pools, trades,);
+diesel::allow_tables_to_appear_in_same_query!(
+    api_keys,
+    block_cursors,
+    coins,
+    deployment_jobs,
+    notify_payloads,
+    pool_prices,
+    pools,
+    signature_nonces,
+    trades,
+    wei_in_updates,
+);
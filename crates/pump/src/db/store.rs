@@ -1,21 +1,49 @@
 use alloy_primitives::Address;
 use bigdecimal::BigDecimal;
-use diesel::{expression_methods::ExpressionMethods, prelude::*, sql_query, sql_types::Text};
+use chrono::NaiveDateTime;
+use diesel::{
+    dsl::{now, IntervalDsl},
+    expression_methods::ExpressionMethods,
+    prelude::*,
+    sql_query,
+    sql_types::{BigInt, Integer, Text},
+};
 use std::{collections::HashMap, str::FromStr};
 
 use crate::{
     client::pool,
     contract::SolidityCoin,
     db::{
-        models::{Coin, HallOfFameRow, NewCoin, NewPoolPrice, Pool, PoolPriceData, Trade},
+        models::{
+            ApiKey, Coin, DeploymentJob, DeploymentJobChanges, DeploymentStatus, HallOfFameRow,
+            NewApiKey, NewCoin, NewPoolPrice, NewWeiInUpdate, Pool, PoolPriceData, Trade, TradeRow,
+            WeiInUpdateRow,
+        },
         schema::{
+            api_keys::{self as api_keys_schema, dsl::api_keys as api_keys_table},
+            block_cursors::{
+                self as block_cursors_schema, dsl::block_cursors as block_cursors_table,
+            },
             coins::{self as coins_schema, dsl::coins as coins_table},
+            deployment_jobs::{
+                self as deployment_jobs_schema, dsl::deployment_jobs as deployment_jobs_table,
+            },
+            notify_payloads::{
+                self as notify_payloads_schema, dsl::notify_payloads as notify_payloads_table,
+            },
             pool_prices::{self as pool_prices_schema, dsl::pool_prices as pool_prices_table},
             pools::{self as pools_schema, dsl::pools as pools_table},
+            signature_nonces::{
+                self as signature_nonces_schema, dsl::signature_nonces as signature_nonces_table,
+            },
             trades::{self as trades_schema, dsl::trades as trades_table},
+            wei_in_updates::{
+                self as wei_in_updates_schema, dsl::wei_in_updates as wei_in_updates_table,
+            },
         },
     },
     error::PumpError,
+    StoredNotification,
 };
 
 pub fn upsert_unverified_coin(
@@ -40,6 +68,11 @@ pub fn upsert_unverified_coin(
 pub fn get_coin(conn: &mut PgConnection, coin_id: i64) -> Result<Coin, PumpError> {
     Ok(coins_table.filter(coins_schema::id.eq(coin_id)).first(conn)?)
 }
+
+/// Most coins `get_all_coins` returns at once, even when `limit` asks for more
+/// (it used to allow 20,000); page with `maxId` for the rest
+const MAX_COINS_PAGE: usize = 1_000;
+
 pub struct GetAllCoinsParams {
     limit: Option<usize>,
     max_id: Option<i64>,
@@ -82,7 +115,7 @@ pub fn get_all_coins(
 
     Ok(query
         .order(coins_schema::id.desc())
-        .limit(params.limit.unwrap_or(1_000).min(20_000) as i64)
+        .limit(params.limit.unwrap_or(MAX_COINS_PAGE).min(MAX_COINS_PAGE) as i64)
         .load::<Coin>(conn)?)
 }
 
@@ -108,21 +141,204 @@ pub fn get_pool_prices(
         query = query.filter(pool_prices_schema::time.ge(min_timestamp));
     }
 
-    // Add ordering and limit
-    let prices = query
+    // Take the most recent candles, then return them oldest first
+    let mut prices = query
         .select((
             pool_prices_schema::time,
             pool_prices_schema::open,
             pool_prices_schema::high,
             pool_prices_schema::low,
             pool_prices_schema::close,
+            pool_prices_schema::volume_base,
+            pool_prices_schema::volume_quote,
+            pool_prices_schema::trade_count,
         ))
-        .order_by(pool_prices_schema::time.asc())
+        .order_by((pool_prices_schema::time.desc(), pool_prices_schema::id.desc()))
         .limit(limit as i64)
         .load::<PoolPriceData>(conn)?;
+    prices.reverse();
     Ok(prices)
 }
 
+/// Width of the candles returned by `get_pool_candles`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CandleInterval {
+    OneMinute,
+    FiveMinutes,
+    OneHour,
+    OneDay,
+}
+
+impl CandleInterval {
+    pub fn parse(interval: &str) -> Result<CandleInterval, PumpError> {
+        match interval {
+            "1m" => Ok(CandleInterval::OneMinute),
+            "5m" => Ok(CandleInterval::FiveMinutes),
+            "1h" => Ok(CandleInterval::OneHour),
+            "1d" => Ok(CandleInterval::OneDay),
+            _ => Err(PumpError::InvalidParam(format!("interval={}", interval))),
+        }
+    }
+
+    pub fn seconds(&self) -> i64 {
+        match self {
+            CandleInterval::OneMinute => 60,
+            CandleInterval::FiveMinutes => 5 * 60,
+            CandleInterval::OneHour => 60 * 60,
+            CandleInterval::OneDay => 24 * 60 * 60,
+        }
+    }
+
+    /// Start of the bucket containing `time`
+    pub fn bucket(&self, time: i64) -> i64 {
+        time - time.rem_euclid(self.seconds())
+    }
+}
+
+pub struct GetPoolPricesParams {
+    /// None returns the raw per-block candles
+    interval: Option<CandleInterval>,
+    max_ts: Option<i64>,
+    min_ts: Option<i64>,
+    /// Most recent candles to return, 100 unless given and never more than 5000
+    limit: usize,
+}
+
+impl GetPoolPricesParams {
+    pub fn parse(params: HashMap<String, String>) -> Result<GetPoolPricesParams, PumpError> {
+        let interval = match params.get("interval") {
+            Some(interval) => Some(CandleInterval::parse(interval)?),
+            None => None,
+        };
+        let parse_ts = |keys: [&str; 2]| -> Result<Option<i64>, PumpError> {
+            match keys.iter().find_map(|key| params.get(*key).map(|value| (key, value))) {
+                Some((key, value)) => match value.parse::<i64>() {
+                    Ok(ts) => Ok(Some(ts)),
+                    Err(_) => Err(PumpError::InvalidParam(format!("{}={}", key, value))),
+                },
+                None => Ok(None),
+            }
+        };
+        let max_ts = parse_ts(["to", "maxTs"])?;
+        let min_ts = parse_ts(["from", "minTs"])?;
+        let limit = match params.get("limit") {
+            Some(lim) => lim.parse::<usize>().unwrap_or(100),
+            None => 100,
+        };
+        Ok(GetPoolPricesParams { interval, max_ts, min_ts, limit: limit.min(5_000) })
+    }
+}
+
+/// Returns the pool's candles, aggregated into time buckets if an interval is given
+pub fn get_pool_candles(
+    conn: &mut PgConnection,
+    pool: String,
+    params: GetPoolPricesParams,
+) -> Result<Vec<PoolPriceData>, PumpError> {
+    let interval = match params.interval {
+        Some(interval) => interval,
+        None => {
+            return get_pool_prices(conn, pool, params.max_ts, params.min_ts, params.limit);
+        }
+    };
+
+    // first make sure the pool exists
+    let _pool = pools_table.filter(pools_schema::address.eq(&pool)).first::<Pool>(conn)?;
+
+    let sql = r#"
+        SELECT
+            bucket AS time,
+            (ARRAY_AGG(open ORDER BY time ASC, id ASC))[1] AS open,
+            MAX(high) AS high,
+            MIN(low) AS low,
+            (ARRAY_AGG(close ORDER BY time DESC, id DESC))[1] AS close,
+            SUM(volume_base) AS volume_base,
+            SUM(volume_quote) AS volume_quote,
+            SUM(trade_count)::INTEGER AS trade_count
+        FROM (
+            SELECT
+                id, time, open, high, low, close, volume_base, volume_quote, trade_count,
+                time - MOD(time, $2) AS bucket
+            FROM pool_prices
+            WHERE pool = $1 AND time >= $3 AND time <= $4
+        ) block_candles
+        GROUP BY bucket
+        ORDER BY bucket DESC
+        LIMIT $5
+    "#;
+    let mut buckets = sql_query(sql)
+        .bind::<Text, _>(&pool)
+        .bind::<BigInt, _>(interval.seconds())
+        .bind::<BigInt, _>(params.min_ts.unwrap_or(0))
+        .bind::<BigInt, _>(params.max_ts.unwrap_or(i64::MAX))
+        .bind::<BigInt, _>(params.limit as i64)
+        .load::<PoolPriceData>(conn)?;
+    buckets.reverse();
+
+    // candles before the window open at the close we had going into it
+    let first_bucket = match (params.min_ts, buckets.first()) {
+        (Some(min_ts), _) => interval.bucket(min_ts),
+        (None, Some(first)) => first.time,
+        (None, None) => return Ok(buckets),
+    };
+    let previous_close = pool_prices_table
+        .filter(pool_prices_schema::pool.eq(&pool))
+        .filter(pool_prices_schema::time.lt(first_bucket))
+        .order(pool_prices_schema::time.desc())
+        .select(pool_prices_schema::close)
+        .first::<BigDecimal>(conn)
+        .optional()?;
+    let last_bucket = params.max_ts.map(|max_ts| interval.bucket(max_ts));
+
+    let mut candles = fill_buckets(buckets, interval, first_bucket, last_bucket, previous_close);
+    if candles.len() > params.limit {
+        candles.drain(..candles.len() - params.limit);
+    }
+    Ok(candles)
+}
+
+/// Adds a flat candle at the previous close for every bucket without trades,
+/// from `first_bucket` through `last_bucket` (or the last bucket with trades).
+/// Buckets before the first close we know of are left empty
+fn fill_buckets(
+    buckets: Vec<PoolPriceData>,
+    interval: CandleInterval,
+    first_bucket: i64,
+    last_bucket: Option<i64>,
+    mut previous_close: Option<BigDecimal>,
+) -> Vec<PoolPriceData> {
+    let last_bucket = match (last_bucket, buckets.last()) {
+        (Some(last_bucket), _) => last_bucket,
+        (None, Some(last)) => last.time,
+        (None, None) => return buckets,
+    };
+    // only fill within the most recent 5000 buckets
+    let first_bucket = first_bucket.max(last_bucket - 4_999 * interval.seconds());
+
+    let mut candles = Vec::with_capacity(buckets.len());
+    let mut buckets = buckets.into_iter().peekable();
+    // candles before the window only tell us where it opens
+    while let Some(candle) = buckets.next_if(|candle| candle.time < first_bucket) {
+        previous_close = Some(candle.close);
+    }
+    let mut time = first_bucket;
+    while time <= last_bucket {
+        match buckets.next_if(|candle| candle.time == time) {
+            Some(candle) => {
+                previous_close = Some(candle.close.clone());
+                candles.push(candle);
+            }
+            None => {
+                if let Some(close) = previous_close.as_ref() {
+                    candles.push(PoolPriceData::flat(time, close.clone()));
+                }
+            }
+        }
+        time += interval.seconds();
+    }
+    candles
+}
+
 pub fn update_coin(
     conn: &mut PgConnection,
     coin_id: i64,
@@ -191,6 +407,14 @@ pub fn graduate_coin(conn: &mut PgConnection, coin_id: i64) -> Result<(), PumpEr
     }
 }
 
+/// Undoes `graduate_coin` for a coin whose graduation was orphaned by a reorg
+pub fn revert_graduation(conn: &mut PgConnection, coin_id: i64) -> Result<(), PumpError> {
+    diesel::update(coins_table.filter(coins_schema::id.eq(coin_id)))
+        .set((coins_schema::graduated.eq(false),))
+        .execute(conn)?;
+    Ok(())
+}
+
 pub fn update_deployed_pool(
     conn: &mut PgConnection,
     coin_id: i64,
@@ -205,6 +429,89 @@ pub fn update_deployed_pool(
     }
 }
 
+/// Undoes `update_deployed_pool` for a coin whose deployment was orphaned by a reorg
+pub fn clear_deployed_pool(conn: &mut PgConnection, coin_id: i64) -> Result<(), PumpError> {
+    diesel::update(coins_table.filter(coins_schema::id.eq(coin_id)))
+        .set((coins_schema::deployed_pool.eq(None::<String>),))
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Queues a graduated coin to be deployed to the dex, unless it already has a job
+pub fn enqueue_deployment(conn: &mut PgConnection, coin_id: i64) -> Result<usize, PumpError> {
+    let count = diesel::insert_into(deployment_jobs_table)
+        .values(deployment_jobs_schema::coin_id.eq(coin_id))
+        .on_conflict(deployment_jobs_schema::coin_id)
+        .do_nothing()
+        .execute(conn)?;
+    Ok(count)
+}
+
+/// Queues every coin that graduated but was never deployed
+/// and has no job, e.g. because the listener was down when it graduated
+pub fn enqueue_undeployed(conn: &mut PgConnection) -> Result<usize, PumpError> {
+    let count = sql_query(
+        r#"
+        INSERT INTO deployment_jobs (coin_id)
+        SELECT id FROM coins
+        WHERE graduated = true AND deployed_pool IS NULL
+        ON CONFLICT (coin_id) DO NOTHING
+        "#,
+    )
+    .execute(conn)?;
+    Ok(count)
+}
+
+/// Marks jobs as deployed once the listener has recorded their coin's pool
+pub fn complete_deployments(conn: &mut PgConnection) -> Result<usize, PumpError> {
+    let count = sql_query(
+        r#"
+        UPDATE deployment_jobs j
+        SET status = 'deployed', updated_at = NOW()
+        FROM coins c
+        WHERE c.id = j.coin_id
+            AND c.deployed_pool IS NOT NULL
+            AND j.status <> 'deployed'
+        "#,
+    )
+    .execute(conn)?;
+    Ok(count)
+}
+
+/// Jobs that are waiting to be sent or checked on, oldest first
+pub fn get_due_deployments(
+    conn: &mut PgConnection,
+    limit: i64,
+) -> Result<Vec<DeploymentJob>, PumpError> {
+    let statuses = [DeploymentStatus::Pending.as_str(), DeploymentStatus::Submitted.as_str()];
+    let jobs = deployment_jobs_table
+        .filter(deployment_jobs_schema::status.eq_any(statuses))
+        .filter(deployment_jobs_schema::next_attempt_at.le(now))
+        .order(deployment_jobs_schema::next_attempt_at.asc())
+        .limit(limit)
+        .select(DeploymentJob::as_select())
+        .load(conn)?;
+    Ok(jobs)
+}
+
+pub fn update_deployment_job(
+    conn: &mut PgConnection,
+    coin_id: i64,
+    changes: DeploymentJobChanges,
+) -> Result<(), PumpError> {
+    diesel::update(deployment_jobs_table.filter(deployment_jobs_schema::coin_id.eq(coin_id)))
+        .set((changes, deployment_jobs_schema::updated_at.eq(now)))
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Drops the job for a coin whose graduation was orphaned by a reorg
+pub fn delete_deployment_job(conn: &mut PgConnection, coin_id: i64) -> Result<(), PumpError> {
+    diesel::delete(deployment_jobs_table.filter(deployment_jobs_schema::coin_id.eq(coin_id)))
+        .execute(conn)?;
+    Ok(())
+}
+
 pub fn upsert_deployed_pool(conn: &mut PgConnection, pool: Pool) -> Result<usize, PumpError> {
     let rows_affected = diesel::insert_into(pools_table)
         .values(pool)
@@ -214,8 +521,21 @@ pub fn upsert_deployed_pool(conn: &mut PgConnection, pool: Pool) -> Result<usize
     Ok(rows_affected)
 }
 
-pub fn add_price(conn: &mut PgConnection, price: NewPoolPrice) -> Result<usize, PumpError> {
-    let count = diesel::insert_into(pool_prices_table).values(&price).execute(conn)?;
+/// Returns the new row's id, so it can be deleted exactly if its block is orphaned
+pub fn add_price(conn: &mut PgConnection, price: NewPoolPrice) -> Result<i64, PumpError> {
+    let id = diesel::insert_into(pool_prices_table)
+        .values(&price)
+        .returning(pool_prices_schema::id)
+        .get_result(conn)?;
+    Ok(id)
+}
+
+pub fn delete_prices(conn: &mut PgConnection, ids: &[i64]) -> Result<usize, PumpError> {
+    if ids.is_empty() {
+        return Ok(0);
+    }
+    let count = diesel::delete(pool_prices_table.filter(pool_prices_schema::id.eq_any(ids)))
+        .execute(conn)?;
     Ok(count)
 }
 
@@ -270,15 +590,163 @@ pub fn load_pools(conn: &mut PgConnection) -> Result<HashMap<Address, pool::Pool
     Ok(pools)
 }
 
+/// Inserts the trade unless we've already recorded its log
 pub fn add_trade(conn: &mut PgConnection, trade: &Trade) -> Result<(), PumpError> {
     diesel::insert_into(trades_table)
         .values(trade)
-        .on_conflict(trades_schema::id)
+        .on_conflict((trades_schema::tx, trades_schema::log_index))
         .do_nothing()
         .execute(conn)?;
     Ok(())
 }
 
+pub struct GetTradesParams {
+    limit: usize,
+    /// (time, id) of the last trade on the previous page
+    cursor: Option<(i64, i64)>,
+}
+
+impl GetTradesParams {
+    pub fn parse(params: HashMap<String, String>) -> Result<GetTradesParams, PumpError> {
+        let limit = match params.get("limit") {
+            Some(lim) => lim.parse::<usize>().unwrap_or(50),
+            None => 50,
+        };
+        let cursor = match params.get("cursor") {
+            Some(cursor) => Some(parse_trade_cursor(cursor)?),
+            None => None,
+        };
+        Ok(GetTradesParams { limit: limit.clamp(1, 500), cursor })
+    }
+}
+
+/// Cursors look like `<time>_<id>`
+fn parse_trade_cursor(cursor: &str) -> Result<(i64, i64), PumpError> {
+    let invalid = || PumpError::InvalidParam(format!("cursor={}", cursor));
+    let (time, id) = cursor.split_once('_').ok_or_else(invalid)?;
+    Ok((time.parse().map_err(|_| invalid())?, id.parse().map_err(|_| invalid())?))
+}
+
+fn trade_cursor(trade: &TradeRow) -> String {
+    format!("{}_{}", trade.time, trade.id)
+}
+
+pub struct TradesPage {
+    pub trades: Vec<TradeRow>,
+    /// pass this as `cursor` to get the next page, None on the last page
+    pub next_cursor: Option<String>,
+}
+
+/// Returns a page of the pool's trades, newest first
+pub fn get_pool_trades(
+    conn: &mut PgConnection,
+    pool: Address,
+    params: GetTradesParams,
+) -> Result<TradesPage, PumpError> {
+    let query = trades_table.filter(trades_schema::pool.eq(pool.to_string())).into_boxed();
+    get_trades_page(conn, query, params)
+}
+
+/// Returns a page of the trader's trades across every pool, newest first
+pub fn get_trader_trades(
+    conn: &mut PgConnection,
+    trader: Address,
+    params: GetTradesParams,
+) -> Result<TradesPage, PumpError> {
+    let query = trades_table.filter(trades_schema::trader.eq(trader.to_string())).into_boxed();
+    get_trades_page(conn, query, params)
+}
+
+fn get_trades_page(
+    conn: &mut PgConnection,
+    mut query: trades_schema::BoxedQuery<'static, diesel::pg::Pg>,
+    params: GetTradesParams,
+) -> Result<TradesPage, PumpError> {
+    if let Some((time, id)) = params.cursor {
+        query = query.filter(
+            trades_schema::time
+                .lt(time)
+                .or(trades_schema::time.eq(time).and(trades_schema::id.lt(id))),
+        );
+    }
+    let trades = query
+        .order((trades_schema::time.desc(), trades_schema::id.desc()))
+        .limit(params.limit as i64)
+        .select(TradeRow::as_select())
+        .load::<TradeRow>(conn)?;
+    let next_cursor = match trades.len() == params.limit {
+        true => trades.last().map(trade_cursor),
+        false => None,
+    };
+    Ok(TradesPage { trades, next_cursor })
+}
+
+/// Returns the pools with the given addresses, keyed by address
+pub fn get_pools(
+    conn: &mut PgConnection,
+    addresses: &[String],
+) -> Result<HashMap<String, pool::Pool>, PumpError> {
+    let pool_vec: Vec<Pool> =
+        pools_table.filter(pools_schema::address.eq_any(addresses)).load(conn)?;
+    pool_vec.into_iter().map(|pool| Ok((pool.address.clone(), pool.try_into()?))).collect()
+}
+
+/// Deletes the trades with these (tx, log index) keys
+pub fn delete_trades(conn: &mut PgConnection, keys: &[(String, i64)]) -> Result<usize, PumpError> {
+    let mut count = 0;
+    for (tx, log_index) in keys {
+        count += diesel::delete(
+            trades_table
+                .filter(trades_schema::tx.eq(tx))
+                .filter(trades_schema::log_index.eq(log_index)),
+        )
+        .execute(conn)?;
+    }
+    Ok(count)
+}
+
+pub fn add_wei_in_update(
+    conn: &mut PgConnection,
+    update: &NewWeiInUpdate,
+) -> Result<(), PumpError> {
+    diesel::insert_into(wei_in_updates_table)
+        .values(update)
+        .on_conflict((wei_in_updates_schema::tx, wei_in_updates_schema::log_index))
+        .do_nothing()
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Deletes the updates with these (tx, log index) keys
+pub fn delete_wei_in_updates(
+    conn: &mut PgConnection,
+    keys: &[(String, i64)],
+) -> Result<usize, PumpError> {
+    let mut count = 0;
+    for (tx, log_index) in keys {
+        count += diesel::delete(
+            wei_in_updates_table
+                .filter(wei_in_updates_schema::tx.eq(tx))
+                .filter(wei_in_updates_schema::log_index.eq(log_index)),
+        )
+        .execute(conn)?;
+    }
+    Ok(count)
+}
+
+/// Every purchase and refund on the coin's bonding curve, oldest first
+pub fn get_coin_progress(
+    conn: &mut PgConnection,
+    coin_id: i64,
+) -> Result<Vec<WeiInUpdateRow>, PumpError> {
+    let rows = wei_in_updates_table
+        .filter(wei_in_updates_schema::coin_id.eq(coin_id))
+        .order((wei_in_updates_schema::block_number.asc(), wei_in_updates_schema::log_index.asc()))
+        .select(WeiInUpdateRow::as_select())
+        .load(conn)?;
+    Ok(rows)
+}
+
 pub fn update_wei_in(
     conn: &mut PgConnection,
     coin_id: i64,
@@ -290,6 +758,16 @@ pub fn update_wei_in(
     Ok(())
 }
 
+/// Returns the id of the coin deployed to the pool, if we have it
+pub fn get_coin_id_by_pool(conn: &mut PgConnection, pool: &str) -> Result<Option<i64>, PumpError> {
+    let coin_id = coins_table
+        .filter(coins_schema::deployed_pool.eq(pool))
+        .select(coins_schema::id)
+        .first::<i64>(conn)
+        .optional()?;
+    Ok(coin_id)
+}
+
 pub fn get_coin_by_address(conn: &mut PgConnection, address: String) -> Result<Coin, PumpError> {
     // Normalize the address by converting to lowercase and trimming whitespace
     let normalized_address = address.to_lowercase().trim().to_string();
@@ -301,3 +779,240 @@ pub fn get_coin_by_address(conn: &mut PgConnection, address: String) -> Result<C
 
     Ok(result)
 }
+
+/// Returns the last block the listener fully processed on this chain, if any
+pub fn get_block_cursor(conn: &mut PgConnection, chain_id: u64) -> Result<Option<u64>, PumpError> {
+    let block_number = block_cursors_table
+        .filter(block_cursors_schema::chain_id.eq(chain_id as i32))
+        .select(block_cursors_schema::block_number)
+        .first::<i64>(conn)
+        .optional()?;
+    Ok(block_number.map(|n| n as u64))
+}
+
+/// Postgres rejects notification payloads this large
+const MAX_NOTIFY_PAYLOAD: usize = 8_000;
+
+/// Sends a Postgres notification, delivered when the current transaction commits.
+/// A payload too big for a notification goes in `notify_payloads`,
+/// and a `StoredNotification` with its id is sent instead
+pub fn notify(conn: &mut PgConnection, channel: &str, payload: &str) -> Result<(), PumpError> {
+    let stored;
+    let payload = if payload.len() >= MAX_NOTIFY_PAYLOAD {
+        // every server has long since fetched these
+        diesel::delete(
+            notify_payloads_table.filter(notify_payloads_schema::created_at.lt(now - 1.days())),
+        )
+        .execute(conn)?;
+        let stored_id = diesel::insert_into(notify_payloads_table)
+            .values(notify_payloads_schema::payload.eq(payload))
+            .returning(notify_payloads_schema::id)
+            .get_result::<i64>(conn)?;
+        stored = serde_json::to_string(&StoredNotification { stored_id })
+            .expect("StoredNotification always serializes");
+        &stored
+    } else {
+        payload
+    };
+    sql_query("SELECT pg_notify($1, $2)")
+        .bind::<Text, _>(channel)
+        .bind::<Text, _>(payload)
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Advances the chain's cursor. Never moves it backwards, see `reset_block_cursor`
+pub fn set_block_cursor(
+    conn: &mut PgConnection,
+    chain_id: u64,
+    block_number: u64,
+) -> Result<(), PumpError> {
+    let sql = "INSERT INTO block_cursors (chain_id, block_number) VALUES ($1, $2)
+        ON CONFLICT (chain_id) DO UPDATE
+        SET block_number = GREATEST(block_cursors.block_number, excluded.block_number),
+            updated_at = now()";
+    sql_query(sql)
+        .bind::<Integer, _>(chain_id as i32)
+        .bind::<BigInt, _>(block_number as i64)
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Moves the chain's cursor back to `block_number` after a reorg
+pub fn reset_block_cursor(
+    conn: &mut PgConnection,
+    chain_id: u64,
+    block_number: u64,
+) -> Result<(), PumpError> {
+    diesel::update(block_cursors_table.filter(block_cursors_schema::chain_id.eq(chain_id as i32)))
+        .set((
+            block_cursors_schema::block_number.eq(block_number as i64),
+            block_cursors_schema::updated_at.eq(now),
+        ))
+        .execute(conn)?;
+    Ok(())
+}
+
+pub fn create_api_key(conn: &mut PgConnection, key: NewApiKey) -> Result<ApiKey, PumpError> {
+    let key = diesel::insert_into(api_keys_table)
+        .values(key)
+        .returning(ApiKey::as_returning())
+        .get_result(conn)?;
+    Ok(key)
+}
+
+/// The unrevoked key with this hash, if there is one
+pub fn get_api_key(conn: &mut PgConnection, key_hash: &str) -> Result<Option<ApiKey>, PumpError> {
+    let key = api_keys_table
+        .filter(api_keys_schema::key_hash.eq(key_hash))
+        .filter(api_keys_schema::revoked_at.is_null())
+        .select(ApiKey::as_select())
+        .first(conn)
+        .optional()?;
+    Ok(key)
+}
+
+pub fn get_api_keys(conn: &mut PgConnection) -> Result<Vec<ApiKey>, PumpError> {
+    let keys =
+        api_keys_table.order(api_keys_schema::id.asc()).select(ApiKey::as_select()).load(conn)?;
+    Ok(keys)
+}
+
+pub fn touch_api_key(conn: &mut PgConnection, id: i64) -> Result<(), PumpError> {
+    diesel::update(api_keys_table.filter(api_keys_schema::id.eq(id)))
+        .set(api_keys_schema::last_used_at.eq(now))
+        .execute(conn)?;
+    Ok(())
+}
+
+pub fn revoke_api_key(conn: &mut PgConnection, id: i64) -> Result<usize, PumpError> {
+    let count = diesel::update(api_keys_table.filter(api_keys_schema::id.eq(id)))
+        .set(api_keys_schema::revoked_at.eq(now))
+        .execute(conn)?;
+    Ok(count)
+}
+
+/// Records a signature's nonce, returning false if the signer already used it.
+/// Also forgets expired nonces, whose signatures are rejected anyway
+pub fn use_signature_nonce(
+    conn: &mut PgConnection,
+    signer: Address,
+    nonce: &str,
+    expires_at: NaiveDateTime,
+) -> Result<bool, PumpError> {
+    diesel::delete(signature_nonces_table.filter(signature_nonces_schema::expires_at.lt(now)))
+        .execute(conn)?;
+    let inserted = diesel::insert_into(signature_nonces_table)
+        .values((
+            signature_nonces_schema::signer.eq(signer.to_string()),
+            signature_nonces_schema::nonce.eq(nonce),
+            signature_nonces_schema::expires_at.eq(expires_at),
+        ))
+        .on_conflict((signature_nonces_schema::signer, signature_nonces_schema::nonce))
+        .do_nothing()
+        .execute(conn)?;
+    Ok(inserted == 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINUTE: i64 = 60;
+
+    fn candle(time: i64, open: u32, close: u32) -> PoolPriceData {
+        PoolPriceData {
+            time,
+            open: BigDecimal::from(open),
+            high: BigDecimal::from(open.max(close)),
+            low: BigDecimal::from(open.min(close)),
+            close: BigDecimal::from(close),
+            volume_base: BigDecimal::from(1),
+            volume_quote: BigDecimal::from(1),
+            trade_count: 1,
+        }
+    }
+
+    fn closes(candles: &[PoolPriceData]) -> Vec<(i64, BigDecimal, i32)> {
+        candles.iter().map(|c| (c.time, c.close.clone(), c.trade_count)).collect()
+    }
+
+    #[test]
+    fn test_fill_empty() {
+        let filled = fill_buckets(vec![], CandleInterval::OneMinute, 0, None, None);
+        assert!(filled.is_empty());
+
+        // nothing to carry forward
+        let filled = fill_buckets(vec![], CandleInterval::OneMinute, 0, Some(2 * MINUTE), None);
+        assert!(filled.is_empty());
+
+        let previous = Some(BigDecimal::from(7));
+        let filled = fill_buckets(vec![], CandleInterval::OneMinute, 0, Some(2 * MINUTE), previous);
+        assert_eq!(
+            closes(&filled),
+            vec![
+                (0, BigDecimal::from(7), 0),
+                (MINUTE, BigDecimal::from(7), 0),
+                (2 * MINUTE, BigDecimal::from(7), 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fill_leading_gap() {
+        let buckets = vec![candle(2 * MINUTE, 1, 2)];
+        // buckets before the first close we know of stay empty
+        let filled = fill_buckets(buckets.clone(), CandleInterval::OneMinute, 0, None, None);
+        assert_eq!(closes(&filled), vec![(2 * MINUTE, BigDecimal::from(2), 1)]);
+
+        let previous = Some(BigDecimal::from(5));
+        let filled = fill_buckets(buckets, CandleInterval::OneMinute, 0, None, previous);
+        assert_eq!(
+            closes(&filled),
+            vec![
+                (0, BigDecimal::from(5), 0),
+                (MINUTE, BigDecimal::from(5), 0),
+                (2 * MINUTE, BigDecimal::from(2), 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fill_carries_last_close() {
+        let buckets = vec![candle(0, 1, 2), candle(3 * MINUTE, 2, 4)];
+        let filled = fill_buckets(buckets, CandleInterval::OneMinute, 0, Some(5 * MINUTE), None);
+        assert_eq!(
+            closes(&filled),
+            vec![
+                (0, BigDecimal::from(2), 1),
+                (MINUTE, BigDecimal::from(2), 0),
+                (2 * MINUTE, BigDecimal::from(2), 0),
+                (3 * MINUTE, BigDecimal::from(4), 1),
+                // trailing gap up to the last bucket
+                (4 * MINUTE, BigDecimal::from(4), 0),
+                (5 * MINUTE, BigDecimal::from(4), 0),
+            ]
+        );
+        // flat candles open, high and low at the close
+        let flat = &filled[1];
+        assert_eq!(flat.open, flat.close);
+        assert_eq!(flat.high, flat.close);
+        assert_eq!(flat.low, flat.close);
+    }
+
+    #[test]
+    fn test_fill_limits_buckets() {
+        let day = CandleInterval::OneDay.seconds();
+        let last = 10_000 * day;
+        let buckets = vec![candle(0, 1, 3), candle(last, 3, 4)];
+        let filled = fill_buckets(buckets, CandleInterval::OneDay, 0, None, None);
+        assert_eq!(filled.len(), 5_000);
+        // the candle before the window still carries its close into it
+        let first = &filled[0];
+        assert_eq!(
+            (first.time, first.close.clone(), first.trade_count),
+            (last - 4_999 * day, BigDecimal::from(3), 0)
+        );
+        assert_eq!(closes(&filled[4_999..]), vec![(last, BigDecimal::from(4), 1)]);
+    }
+}
@@ -16,6 +16,8 @@ pub enum ListenerError {
     WrongBlock(u64, u64),
     #[error("Missing transaction hash")]
     MissingTransactionHash,
+    #[error("Missing log index")]
+    MissingLogIndex,
     #[error("IO error: {0:?}")]
     IoError(std::io::Error),
     #[error("Failed to serialize message: {0:?}")]
@@ -1,6 +1,11 @@
 use alloy_primitives::{Address, FixedBytes};
 use alloy_transport::TransportError;
-use axum::{extract::multipart::MultipartError, http::StatusCode, response::IntoResponse};
+use axum::{
+    extract::multipart::MultipartError,
+    http::{header::RETRY_AFTER, StatusCode},
+    response::IntoResponse,
+};
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
 
 use crate::error::listener::ListenerError;
 
@@ -40,6 +45,18 @@ pub enum PumpError {
     FailedToReadFile(String),
     #[error("No block with number: {0:?}")]
     NoBlockWithNumber(u64),
+    #[error("Invalid query parameter: {0}")]
+    InvalidParam(String),
+    #[error("Missing or invalid API key")]
+    Unauthorized,
+    #[error("API key lacks the {0} scope")]
+    Forbidden(String),
+    #[error("Invalid signature: {0}")]
+    InvalidSignature(String),
+    #[error("Signer is not the creator of coin {0}")]
+    NotCoinCreator(i64),
+    #[error("Too many requests, retry in {0}s")]
+    RateLimited(u64),
 }
 
 impl From<ListenerError> for PumpError {
@@ -81,6 +98,10 @@ impl From<diesel::result::Error> for PumpError {
 impl IntoResponse for PumpError {
     fn into_response(self) -> axum::response::Response {
         let msg = format!("{:?}", self);
+        if let PumpError::RateLimited(retry_after) = self {
+            return (StatusCode::TOO_MANY_REQUESTS, [(RETRY_AFTER, retry_after.to_string())], msg)
+                .into_response();
+        }
         let code: StatusCode = self.into();
         return (code, msg).into_response();
     }
@@ -93,6 +114,8 @@ impl Into<StatusCode> for PumpError {
             PumpError::CoinNotFound(_) | PumpError::PairNotFound(_) | PumpError::WethNotFound => {
                 StatusCode::NOT_FOUND
             }
+            // 413 when the upload is over the body limit
+            PumpError::FileUpload(FileUploadError::Multipart(e)) => e.status(),
             PumpError::FileUpload(_) => StatusCode::BAD_REQUEST,
             PumpError::Diesel(e) => match e {
                 // TODO
@@ -104,6 +127,12 @@ impl Into<StatusCode> for PumpError {
             PumpError::FailedToReadFile(_) => StatusCode::INTERNAL_SERVER_ERROR,
             PumpError::NoBlockWithNumber(_) => StatusCode::NOT_FOUND,
             PumpError::CoinNotGraduated(_) => StatusCode::BAD_REQUEST,
+            PumpError::InvalidParam(_) => StatusCode::BAD_REQUEST,
+            PumpError::Unauthorized => StatusCode::UNAUTHORIZED,
+            PumpError::Forbidden(_) => StatusCode::FORBIDDEN,
+            PumpError::InvalidSignature(_) => StatusCode::UNAUTHORIZED,
+            PumpError::NotCoinCreator(_) => StatusCode::FORBIDDEN,
+            PumpError::RateLimited(_) => StatusCode::TOO_MANY_REQUESTS,
         }
     }
 }
@@ -136,4 +165,27 @@ impl PumpError {
     pub fn missing_tx() -> PumpError {
         ListenerError::MissingTransactionHash.into()
     }
+
+    pub fn missing_log_index() -> PumpError {
+        ListenerError::MissingLogIndex.into()
+    }
+
+    /// Errors that might not happen again: lost connections, the node being unreachable,
+    /// and serialization failures. Missing rows or constraint violations fail the same way every time
+    pub fn is_transient(&self) -> bool {
+        match self {
+            PumpError::R2D2(_) | PumpError::TransportError(_) => true,
+            PumpError::Diesel(e) => matches!(
+                e,
+                DieselError::DatabaseError(
+                    DatabaseErrorKind::SerializationFailure
+                        | DatabaseErrorKind::ClosedConnection
+                        | DatabaseErrorKind::UnableToSendCommand,
+                    _
+                ) | DieselError::BrokenTransactionManager
+                    | DieselError::AlreadyInTransaction
+            ),
+            _ => false,
+        }
+    }
 }
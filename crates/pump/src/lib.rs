@@ -2,7 +2,8 @@ use std::path::PathBuf;
 
 use bigdecimal::BigDecimal;
 use cargo_metadata::MetadataCommand;
-use db::models::Coin;
+use client::pool::TradeSide;
+use db::models::{serialize_decimal_as_f64, Coin, PoolPriceData};
 use serde::{Deserialize, Serialize};
 
 pub mod client;
@@ -11,8 +12,30 @@ pub mod db;
 pub mod error;
 
 pub const SOCKET_FILENAME: &str = "listener.sock";
+/// Postgres channel the listener notifies when using `UpdateTransport::Postgres`
+pub const NOTIFY_CHANNEL: &str = "listener_updates";
 
-#[derive(Debug, Serialize, Deserialize)]
+/// How the listener sends updates to the server
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpdateTransport {
+    /// `listener.sock` in the workspace root, so both must run on the same host
+    Socket,
+    /// `pg_notify` on `NOTIFY_CHANNEL`, sent in the same transaction as the block's writes
+    Postgres,
+}
+
+impl UpdateTransport {
+    /// Reads `UPDATE_TRANSPORT`, which is `socket` (the default) or `postgres`
+    pub fn from_env() -> UpdateTransport {
+        match std::env::var("UPDATE_TRANSPORT").as_deref() {
+            Ok("postgres") => UpdateTransport::Postgres,
+            Ok("socket") | Err(_) => UpdateTransport::Socket,
+            Ok(other) => panic!("UPDATE_TRANSPORT must be socket or postgres: {}", other),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", content = "data")]
 #[serde(rename_all = "camelCase")]
 pub enum ListenerUpdate {
@@ -34,6 +57,73 @@ pub enum ListenerUpdate {
         id: i64,
         deployed_pool: String,
     },
+
+    /// Blocks after `fork_block` were orphaned and have been replaced by the canonical chain
+    #[serde(rename_all = "camelCase")]
+    Reorg {
+        fork_block: u64,
+        depth: u64,
+    },
+
+    /// The coin's state after rolling back a reorg
+    CorrectedCoin(Coin),
+
+    Trade(TradeUpdate),
+
+    /// A pool's candle for a single block
+    Candle(CandleUpdate),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CandleUpdate {
+    pub pool: String,
+    pub block_number: u64,
+    #[serde(flatten)]
+    pub candle: PoolPriceData,
+}
+
+/// A swap in one of our pools, relative to the pool's coin
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TradeUpdate {
+    pub pool: String,
+    /// None if the pool's coin isn't in our DB
+    pub coin_id: Option<i64>,
+    pub side: TradeSide,
+    #[serde(serialize_with = "serialize_decimal_as_f64")]
+    pub amount_token: BigDecimal,
+    #[serde(serialize_with = "serialize_decimal_as_f64")]
+    pub amount_eth: BigDecimal,
+    /// ETH per coin
+    #[serde(serialize_with = "serialize_decimal_as_f64")]
+    pub price: BigDecimal,
+    pub trader: String,
+    pub tx: String,
+    pub time: i64,
+}
+
+/// An update as it's written to the listener's socket
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListenerMessage {
+    /// increases by one with every message the listener sends.
+    /// Starts at the listener's startup time in microseconds, so it keeps increasing across restarts
+    #[serde(default)]
+    pub seq: u64,
+    #[serde(flatten)]
+    pub update: ListenerUpdate,
+    /// true until the block that produced the update is confirmed and written to the DB
+    #[serde(default)]
+    pub provisional: bool,
+}
+
+/// Sent on a Postgres channel in place of a payload too big for a notification.
+/// The payload is in the `notify_payloads` row with this id
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StoredNotification {
+    pub stored_id: i64,
 }
 
 /// Returns the workspace root by invoking `cargo metadata`.
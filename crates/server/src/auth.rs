@@ -0,0 +1,143 @@
+use axum::{
+    extract::State,
+    http::{header::AUTHORIZATION, HeaderMap, Request, Uri},
+    middleware::Next,
+    response::Response,
+};
+
+use pump::{
+    db::{
+        models::{hash_api_key, ApiKey, ApiScope},
+        store,
+    },
+    error::PumpError,
+};
+
+use crate::AppState;
+
+const API_KEY_HEADER: &str = "x-api-key";
+
+/// State for `require_scope`: the scope a route needs
+#[derive(Clone)]
+pub(crate) struct RequiredScope {
+    state: AppState,
+    scope: ApiScope,
+}
+
+pub(crate) fn scope(state: &AppState, scope: ApiScope) -> RequiredScope {
+    RequiredScope { state: state.clone(), scope }
+}
+
+/// Reads the key from `Authorization: Bearer <key>` or `X-Api-Key: <key>`.
+/// Other `Authorization` schemes, like Basic auth added by a proxy, don't hide `X-Api-Key`
+fn api_key(headers: &HeaderMap) -> Option<&str> {
+    let bearer = headers
+        .get(AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    if let Some(key) = bearer {
+        return Some(key.trim());
+    }
+    headers.get(API_KEY_HEADER).and_then(|v| v.to_str().ok()).map(str::trim)
+}
+
+/// Checks the key we found for the request's hash: it has to exist, be unrevoked and have `scope`
+fn authorize(api_key: Option<ApiKey>, scope: ApiScope, uri: &Uri) -> Result<ApiKey, PumpError> {
+    let api_key = api_key.filter(|key| key.revoked_at.is_none()).ok_or(PumpError::Unauthorized)?;
+    if !api_key.has_scope(scope) {
+        log::warn!(
+            "API key {} tried to use {} without the {} scope",
+            api_key.name,
+            uri,
+            scope.as_str()
+        );
+        return Err(PumpError::Forbidden(scope.as_str().to_string()));
+    }
+    Ok(api_key)
+}
+
+/// Middleware that rejects requests without an API key with the route's scope
+pub(crate) async fn require_scope<B>(
+    State(required): State<RequiredScope>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Result<Response, PumpError> {
+    let key = api_key(request.headers()).ok_or(PumpError::Unauthorized)?;
+    {
+        let mut conn = required.state.db_conn()?;
+        let api_key = store::get_api_key(&mut conn, &hash_api_key(key))?;
+        let api_key = authorize(api_key, required.scope, request.uri())?;
+        store::touch_api_key(&mut conn, api_key.id)?;
+    }
+    Ok(next.run(request).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use axum::http::HeaderValue;
+    use chrono::NaiveDateTime;
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.insert(*name, HeaderValue::from_static(value));
+        }
+        headers
+    }
+
+    fn key(scopes: &[&str], revoked: bool) -> Option<ApiKey> {
+        Some(ApiKey {
+            id: 1,
+            name: "test".to_string(),
+            key_hash: hash_api_key("secret"),
+            scopes: scopes.iter().map(|scope| scope.to_string()).collect(),
+            created_at: NaiveDateTime::default(),
+            last_used_at: None,
+            revoked_at: revoked.then(NaiveDateTime::default),
+        })
+    }
+
+    fn check(api_key: Option<ApiKey>, scope: ApiScope) -> Result<ApiKey, PumpError> {
+        authorize(api_key, scope, &Uri::from_static("/coin/1/deploy"))
+    }
+
+    #[test]
+    fn test_api_key_headers() {
+        assert_eq!(api_key(&headers(&[("authorization", "Bearer secret ")])), Some("secret"));
+        assert_eq!(api_key(&headers(&[("x-api-key", " secret")])), Some("secret"));
+        assert_eq!(api_key(&headers(&[])), None);
+        // bearer wins
+        let both = headers(&[("authorization", "Bearer secret"), ("x-api-key", "other")]);
+        assert_eq!(api_key(&both), Some("secret"));
+        // a proxy's Basic auth doesn't hide the key
+        let basic = headers(&[("authorization", "Basic dXNlcjpwYXNz"), ("x-api-key", "secret")]);
+        assert_eq!(api_key(&basic), Some("secret"));
+        assert_eq!(api_key(&headers(&[("authorization", "Basic dXNlcjpwYXNz")])), None);
+    }
+
+    #[test]
+    fn test_scopes() {
+        assert!(check(key(&["deploy"], false), ApiScope::Deploy).is_ok());
+        assert!(check(key(&["admin"], false), ApiScope::Deploy).is_ok());
+        assert!(matches!(
+            check(key(&["verify", "sync"], false), ApiScope::Deploy),
+            Err(PumpError::Forbidden(scope)) if scope == "deploy"
+        ));
+        // unknown scopes grant nothing
+        assert!(matches!(
+            check(key(&["root"], false), ApiScope::Sync),
+            Err(PumpError::Forbidden(_))
+        ));
+    }
+
+    #[test]
+    fn test_unknown_and_revoked_keys() {
+        assert!(matches!(check(None, ApiScope::Verify), Err(PumpError::Unauthorized)));
+        assert!(matches!(
+            check(key(&["admin"], true), ApiScope::Verify),
+            Err(PumpError::Unauthorized)
+        ));
+    }
+}
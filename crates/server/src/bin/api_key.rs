@@ -0,0 +1,77 @@
+use pump::{
+    db::{
+        models::{hash_api_key, ApiScope, NewApiKey},
+        pool::{connect, establish_pool},
+        store,
+    },
+    error::PumpError,
+    get_workspace_root,
+};
+use uuid::Uuid;
+
+const USAGE: &str = "usage:
+  api_key create <name> <scope>[,<scope>...]   scopes: verify, sync, deploy, admin
+  api_key list
+  api_key revoke <id>";
+
+fn usage() -> ! {
+    eprintln!("{}", USAGE);
+    std::process::exit(1);
+}
+
+/// Manages the API keys for the server's privileged routes
+fn main() -> Result<(), PumpError> {
+    let workspace_root = get_workspace_root().expect("no workspace root");
+    dotenv::from_path(format!("{}/.env", workspace_root)).ok();
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let mut conn = connect(&establish_pool())?;
+    match args.as_slice() {
+        ["create", name, scopes] => {
+            let scopes: Vec<String> = scopes
+                .split(',')
+                .map(|scope| match ApiScope::parse(scope.trim()) {
+                    Some(scope) => scope.as_str().to_string(),
+                    None => usage(),
+                })
+                .collect();
+            let key = format!("mh_{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple());
+            let api_key = store::create_api_key(
+                &mut conn,
+                NewApiKey { name: name.to_string(), key_hash: hash_api_key(&key), scopes },
+            )?;
+            println!(
+                "Created key {} ({}) with scopes {:?}",
+                api_key.id, api_key.name, api_key.scopes
+            );
+            println!("{}", key);
+            println!("Save it now: only its hash is stored");
+        }
+        ["list"] => {
+            for key in store::get_api_keys(&mut conn)? {
+                let status = match key.revoked_at {
+                    Some(revoked_at) => format!("revoked {}", revoked_at),
+                    None => "active".to_string(),
+                };
+                println!(
+                    "{}\t{}\t{}\t{}\tlast used {:?}",
+                    key.id,
+                    key.name,
+                    key.scopes.join(","),
+                    status,
+                    key.last_used_at
+                );
+            }
+        }
+        ["revoke", id] => {
+            let id = id.parse::<i64>().unwrap_or_else(|_| usage());
+            match store::revoke_api_key(&mut conn, id)? {
+                0 => eprintln!("No key with id {}", id),
+                _ => println!("Revoked key {}", id),
+            }
+        }
+        _ => usage(),
+    }
+    Ok(())
+}
@@ -0,0 +1,258 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::HashSet, str::FromStr};
+
+use pump::{ListenerMessage, ListenerUpdate};
+
+/// Something a websocket client can subscribe to
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub(crate) enum Channel {
+    /// new coins and updates to every coin
+    Coins,
+    /// updates and trades for a single coin
+    Coin { id: i64 },
+    /// a pool's trades and candles
+    Pool { address: String },
+    /// a trader's trades in every pool
+    Trader { address: String },
+}
+
+impl Channel {
+    /// Addresses are matched case-insensitively
+    fn normalized(self) -> Channel {
+        match self {
+            Channel::Pool { address } => Channel::Pool { address: address.to_lowercase() },
+            Channel::Trader { address } => Channel::Trader { address: address.to_lowercase() },
+            channel => channel,
+        }
+    }
+}
+
+/// Parses the `?channels=` form: `coins`, `coin:<id>`, `pool:<address>` or `trader:<address>`
+impl FromStr for Channel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Channel, String> {
+        let channel = match s.split_once(':') {
+            None if s == "coins" => Channel::Coins,
+            Some(("coin", id)) => {
+                Channel::Coin { id: id.parse().map_err(|_| format!("Invalid coin id: {}", id))? }
+            }
+            Some(("pool", address)) => Channel::Pool { address: address.to_string() },
+            Some(("trader", address)) => Channel::Trader { address: address.to_string() },
+            _ => return Err(format!("Unknown channel: {}", s)),
+        };
+        Ok(channel.normalized())
+    }
+}
+
+/// Frames a client sends to change what it receives
+#[derive(Debug, Deserialize)]
+#[serde(tag = "action", rename_all = "camelCase")]
+pub(crate) enum ClientMessage {
+    Subscribe { channels: Vec<Channel> },
+    Unsubscribe { channels: Vec<Channel> },
+}
+
+/// Frames we send back in response to a `ClientMessage`
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", content = "data", rename_all = "camelCase")]
+pub(crate) enum ServerMessage {
+    Subscribed(Vec<Channel>),
+    Error(String),
+    /// the client reconnected with `?since=` but missed more than we keep,
+    /// so it should refetch everything over HTTP
+    #[serde(rename_all = "camelCase")]
+    ResyncRequired {
+        latest_seq: Option<u64>,
+    },
+}
+
+/// A channel set for one client.
+/// Clients that never subscribe get coin updates, which is all `/ws` used to send
+pub(crate) struct Subscriptions {
+    channels: HashSet<Channel>,
+}
+
+impl Default for Subscriptions {
+    fn default() -> Self {
+        Subscriptions { channels: HashSet::from([Channel::Coins]) }
+    }
+}
+
+impl Subscriptions {
+    pub fn new(channels: impl IntoIterator<Item = Channel>) -> Subscriptions {
+        Subscriptions { channels: channels.into_iter().map(Channel::normalized).collect() }
+    }
+
+    /// Reads a comma separated `?channels=` list, so a reconnecting client's replay
+    /// covers the same channels it was subscribed to. Defaults to coin updates
+    pub fn from_query(channels: Option<&String>) -> Result<Subscriptions, String> {
+        match channels {
+            Some(channels) => Ok(Subscriptions::new(
+                channels
+                    .split(',')
+                    .filter(|channel| !channel.is_empty())
+                    .map(Channel::from_str)
+                    .collect::<Result<Vec<_>, _>>()?,
+            )),
+            None => Ok(Subscriptions::default()),
+        }
+    }
+
+    /// Applies the client's message, returning the channels it's now subscribed to
+    pub fn handle(&mut self, message: ClientMessage) -> Vec<Channel> {
+        match message {
+            ClientMessage::Subscribe { channels } => {
+                self.channels.extend(channels.into_iter().map(Channel::normalized));
+            }
+            ClientMessage::Unsubscribe { channels } => {
+                for channel in channels {
+                    self.channels.remove(&channel.normalized());
+                }
+            }
+        }
+        self.channels.iter().cloned().collect()
+    }
+
+    pub fn wants(&self, broadcast: &Broadcast) -> bool {
+        match &broadcast.channels {
+            Some(channels) => channels.iter().any(|channel| self.channels.contains(channel)),
+            None => true,
+        }
+    }
+}
+
+/// A listener message, with the channels it's delivered on
+#[derive(Debug)]
+pub(crate) struct Broadcast {
+    pub seq: u64,
+    pub text: String,
+    /// None for messages every client gets
+    pub channels: Option<Vec<Channel>>,
+}
+
+impl Broadcast {
+    pub fn new(message: &ListenerMessage) -> Result<Broadcast, serde_json::Error> {
+        let text = serde_json::to_string(message)?;
+        Ok(Broadcast { seq: message.seq, text, channels: channels(&message.update) })
+    }
+
+    /// Parses one `ListenerMessage` as JSON, ignoring trailing whitespace like the frame's newline
+    pub fn parse(frame: &[u8]) -> Result<Broadcast, serde_json::Error> {
+        let message: ListenerMessage = serde_json::from_slice(frame.trim_ascii_end())?;
+        Broadcast::new(&message)
+    }
+}
+
+fn coin_channels(id: i64) -> Option<Vec<Channel>> {
+    Some(vec![Channel::Coins, Channel::Coin { id }])
+}
+
+fn channels(update: &ListenerUpdate) -> Option<Vec<Channel>> {
+    match update {
+        ListenerUpdate::VerifiedCoin(coin) | ListenerUpdate::CorrectedCoin(coin) => {
+            coin_channels(coin.id)
+        }
+        ListenerUpdate::WeiInUpdated { id, .. }
+        | ListenerUpdate::GraduatedCoin { id }
+        | ListenerUpdate::DeployedToDex { id, .. } => coin_channels(*id),
+        // everyone needs to know their data may have changed
+        ListenerUpdate::Reorg { .. } => None,
+        ListenerUpdate::Trade(trade) => {
+            let mut channels = vec![
+                Channel::Pool { address: trade.pool.to_lowercase() },
+                Channel::Trader { address: trade.trader.to_lowercase() },
+            ];
+            if let Some(id) = trade.coin_id {
+                channels.push(Channel::Coin { id });
+            }
+            Some(channels)
+        }
+        ListenerUpdate::Candle(candle) => {
+            Some(vec![Channel::Pool { address: candle.pool.to_lowercase() }])
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn broadcast(update: ListenerUpdate) -> Broadcast {
+        Broadcast::new(&ListenerMessage { seq: 1, update, provisional: false }).unwrap()
+    }
+
+    fn pool_broadcast(address: &str) -> Broadcast {
+        Broadcast {
+            seq: 1,
+            text: String::new(),
+            channels: Some(vec![Channel::Pool { address: address.to_lowercase() }]),
+        }
+    }
+
+    fn pool(address: &str) -> Channel {
+        Channel::Pool { address: address.to_string() }
+    }
+
+    #[test]
+    fn test_default_subscriptions() {
+        let subscriptions = Subscriptions::default();
+        assert!(subscriptions.wants(&broadcast(ListenerUpdate::GraduatedCoin { id: 1 })));
+        assert!(!subscriptions.wants(&pool_broadcast("0xAbC")));
+        // everyone gets reorgs
+        let reorg = broadcast(ListenerUpdate::Reorg { fork_block: 10, depth: 2 });
+        assert!(reorg.channels.is_none());
+        assert!(Subscriptions::new(Vec::<Channel>::new()).wants(&reorg));
+    }
+
+    #[test]
+    fn test_subscribe_and_unsubscribe() {
+        let mut subscriptions = Subscriptions::default();
+        let channels =
+            subscriptions.handle(ClientMessage::Subscribe { channels: vec![pool("0xAbC")] });
+        assert_eq!(channels.len(), 2);
+        // addresses match case-insensitively
+        assert!(channels.contains(&pool("0xabc")));
+        assert!(subscriptions.wants(&pool_broadcast("0xABC")));
+        assert!(!subscriptions.wants(&pool_broadcast("0xdef")));
+
+        let channels = subscriptions
+            .handle(ClientMessage::Unsubscribe { channels: vec![pool("0xABC"), Channel::Coins] });
+        assert!(channels.is_empty());
+        assert!(!subscriptions.wants(&pool_broadcast("0xabc")));
+        assert!(!subscriptions.wants(&broadcast(ListenerUpdate::GraduatedCoin { id: 1 })));
+    }
+
+    #[test]
+    fn test_coin_channel() {
+        let subscriptions = Subscriptions::new([Channel::Coin { id: 2 }]);
+        assert!(subscriptions.wants(&broadcast(ListenerUpdate::GraduatedCoin { id: 2 })));
+        assert!(!subscriptions.wants(&broadcast(ListenerUpdate::GraduatedCoin { id: 3 })));
+    }
+
+    #[test]
+    fn test_channels_query() {
+        let subscriptions = Subscriptions::from_query(None).unwrap();
+        assert_eq!(subscriptions.channels, HashSet::from([Channel::Coins]));
+
+        let query = "coins,coin:5,pool:0xAbC,trader:0xDeF".to_string();
+        let subscriptions = Subscriptions::from_query(Some(&query)).unwrap();
+        assert_eq!(
+            subscriptions.channels,
+            HashSet::from([
+                Channel::Coins,
+                Channel::Coin { id: 5 },
+                pool("0xabc"),
+                Channel::Trader { address: "0xdef".to_string() },
+            ])
+        );
+
+        // only what's asked for, so a client can drop coin updates
+        let subscriptions = Subscriptions::from_query(Some(&String::new())).unwrap();
+        assert!(subscriptions.channels.is_empty());
+
+        assert!(Subscriptions::from_query(Some(&"coin:x".to_string())).is_err());
+        assert!(Subscriptions::from_query(Some(&"candles".to_string())).is_err());
+    }
+}
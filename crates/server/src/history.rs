@@ -0,0 +1,111 @@
+use std::{collections::VecDeque, sync::Arc};
+
+use crate::channel::Broadcast;
+
+const DEFAULT_HISTORY_SIZE: usize = 1_000;
+
+/// The most recent listener messages, so clients that reconnect can catch up
+pub(crate) struct History {
+    messages: VecDeque<Arc<Broadcast>>,
+    capacity: usize,
+}
+
+pub(crate) enum Replay {
+    Messages(Vec<Arc<Broadcast>>),
+    /// we no longer have everything after the client's sequence number
+    ResyncRequired,
+}
+
+impl History {
+    /// Reads the size from `WS_HISTORY_SIZE`
+    pub fn from_env() -> History {
+        let capacity = std::env::var("WS_HISTORY_SIZE")
+            .ok()
+            .and_then(|size| size.parse::<usize>().ok())
+            .unwrap_or(DEFAULT_HISTORY_SIZE);
+        History { messages: VecDeque::with_capacity(capacity), capacity: capacity.max(1) }
+    }
+
+    pub fn push(&mut self, message: Arc<Broadcast>) {
+        if self.messages.len() >= self.capacity {
+            self.messages.pop_front();
+        }
+        self.messages.push_back(message);
+    }
+
+    pub fn latest_seq(&self) -> Option<u64> {
+        self.messages.back().map(|message| message.seq)
+    }
+
+    pub fn contains(&self, seq: u64) -> bool {
+        self.messages.binary_search_by_key(&seq, |message| message.seq).is_ok()
+    }
+
+    pub fn clear(&mut self) {
+        self.messages.clear();
+    }
+
+    /// Returns every message after `seq`.
+    /// Sequence numbers are consecutive within a listener run, so if we don't have `seq + 1`
+    /// the client missed something we can't give it
+    pub fn since(&self, seq: u64) -> Replay {
+        if self.latest_seq() == Some(seq) {
+            return Replay::Messages(vec![]);
+        }
+        match self.messages.iter().position(|message| message.seq == seq + 1) {
+            Some(start) => Replay::Messages(self.messages.range(start..).cloned().collect()),
+            None => Replay::ResyncRequired,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn history(capacity: usize, seqs: impl IntoIterator<Item = u64>) -> History {
+        let mut history = History { messages: VecDeque::new(), capacity };
+        for seq in seqs {
+            history.push(Arc::new(Broadcast { seq, text: seq.to_string(), channels: None }));
+        }
+        history
+    }
+
+    /// The replayed sequence numbers, or None if the client has to resync
+    fn replayed(history: &History, since: u64) -> Option<Vec<u64>> {
+        match history.since(since) {
+            Replay::Messages(messages) => Some(messages.iter().map(|m| m.seq).collect()),
+            Replay::ResyncRequired => None,
+        }
+    }
+
+    #[test]
+    fn test_caught_up() {
+        let history = history(3, 1..=3);
+        assert_eq!(history.latest_seq(), Some(3));
+        assert_eq!(replayed(&history, 3), Some(vec![]));
+    }
+
+    #[test]
+    fn test_replays_missed() {
+        let history = history(3, 1..=3);
+        assert_eq!(replayed(&history, 1), Some(vec![2, 3]));
+    }
+
+    #[test]
+    fn test_evicted() {
+        // holds 3, 4 and 5
+        let history = history(3, 1..=5);
+        // the oldest message we have is the next one the client needs
+        assert_eq!(replayed(&history, 2), Some(vec![3, 4, 5]));
+        // 2 was evicted
+        assert_eq!(replayed(&history, 1), None);
+    }
+
+    #[test]
+    fn test_unknown_seq() {
+        // e.g. from before the listener restarted
+        assert_eq!(replayed(&history(3, 10..=12), 20), None);
+        assert_eq!(replayed(&history(3, Vec::new()), 1), None);
+    }
+}
@@ -1,13 +1,17 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, str::FromStr};
+
+use alloy_primitives::Address;
 
 use aws_sdk_s3::primitives::ByteStream;
 use axum::{
     extract::{Multipart, Path, Query, State},
-    http::StatusCode,
+    http::{HeaderMap, StatusCode},
     response::IntoResponse,
     Json,
 };
+use bigdecimal::BigDecimal;
 use chrono::Utc;
+use diesel::PgConnection;
 use serde::Serialize;
 
 use pump::{
@@ -15,7 +19,10 @@ use pump::{
     error::PumpError,
 };
 
-use crate::AppState;
+use crate::{
+    ownership::{self, CoinWrite, OwnershipProof},
+    AppState,
+};
 
 #[derive(Serialize)]
 struct CoinResponse {
@@ -32,16 +39,106 @@ pub(crate) async fn get_coin_handler(
     Ok(Json(CoinResponse { coin }).into_response())
 }
 
-/// Handler for GET /coin/:id/snippet?length=50
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ProgressResponse {
+    coin_id: i64,
+    wei_graduation: BigDecimal,
+    graduated: bool,
+    points: Vec<models::ProgressPoint>,
+}
+
+/// Handler for GET /config: the deployed PumpRand's bonding curve parameters
+pub(crate) async fn get_pump_config(State(state): State<AppState>) -> impl IntoResponse {
+    Json(state.pump_client.config.clone())
+}
+
+/// Handler for GET /coin/:id/progress
+pub(crate) async fn get_coin_progress(
+    Path(coin_id): Path<i64>,
+    State(state): State<AppState>,
+) -> Result<impl IntoResponse, PumpError> {
+    let mut conn = state.db_conn()?;
+    let coin = store::get_coin(&mut conn, coin_id)?;
+    let rows = store::get_coin_progress(&mut conn, coin_id)?;
+    Ok(Json(ProgressResponse {
+        coin_id,
+        wei_graduation: state.pump_client.config.wei_graduation.clone(),
+        graduated: coin.graduated,
+        points: rows.into_iter().map(models::ProgressPoint::from).collect(),
+    })
+    .into_response())
+}
+
+/// Handler for GET /pool/:pool/prices?interval=5m&from=&to=
 pub(crate) async fn get_pool_prices(
     Path(pool): Path<String>,
     State(state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
 ) -> Result<impl IntoResponse, PumpError> {
+    let params = store::GetPoolPricesParams::parse(params)?;
     let mut conn = state.db_conn()?;
-    let prices = store::get_pool_prices(&mut conn, pool, None, None, 100)?;
+    let prices = store::get_pool_candles(&mut conn, pool, params)?;
     Ok(Json::<Vec<models::PoolPriceData>>(prices).into_response())
 }
 
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TradesResponse {
+    trades: Vec<models::TradeData>,
+    next_cursor: Option<String>,
+}
+
+fn parse_address_param(address: &str) -> Result<Address, PumpError> {
+    Address::from_str(address).map_err(|_| PumpError::InvalidParam(address.to_string()))
+}
+
+fn trades_response(
+    conn: &mut PgConnection,
+    page: store::TradesPage,
+    weth: Address,
+) -> Result<TradesResponse, PumpError> {
+    let addresses: Vec<String> = page.trades.iter().map(|trade| trade.pool.clone()).collect();
+    let pools = store::get_pools(conn, &addresses)?;
+    let trades = page
+        .trades
+        .into_iter()
+        .filter_map(|trade| {
+            let pool = pools.get(&trade.pool)?;
+            Some(models::TradeData::new(trade, pool, weth))
+        })
+        .collect();
+    Ok(TradesResponse { trades, next_cursor: page.next_cursor })
+}
+
+/// Handler for GET /pool/:pool/trades?cursor=&limit=50
+pub(crate) async fn get_pool_trades(
+    Path(pool): Path<String>,
+    State(state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<impl IntoResponse, PumpError> {
+    let pool = parse_address_param(&pool)?;
+    let params = store::GetTradesParams::parse(params)?;
+    let mut conn = state.db_conn()?;
+    let page = store::get_pool_trades(&mut conn, pool, params)?;
+    let response = trades_response(&mut conn, page, state.pump_client.weth())?;
+    Ok(Json(response).into_response())
+}
+
+/// Handler for GET /trader/:address/trades?cursor=&limit=50
+pub(crate) async fn get_trader_trades(
+    Path(trader): Path<String>,
+    State(state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<impl IntoResponse, PumpError> {
+    let trader = parse_address_param(&trader)?;
+    let params = store::GetTradesParams::parse(params)?;
+    let mut conn = state.db_conn()?;
+    let page = store::get_trader_trades(&mut conn, trader, params)?;
+    let response = trades_response(&mut conn, page, state.pump_client.weth())?;
+    Ok(Json(response).into_response())
+}
+
 pub(crate) async fn verify_coin_handler(
     Path(coin_id): Path<i64>,
     State(state): State<AppState>,
@@ -85,6 +182,8 @@ pub(crate) async fn sync_coin(
         token_0: sol_pool.token_0.to_string(),
         token_1: sol_pool.token_1.to_string(),
         created_at: Utc::now().naive_utc(),
+        decimals_0: sol_pool.decimals_0 as i32,
+        decimals_1: sol_pool.decimals_1 as i32,
     };
     store::upsert_deployed_pool(&mut conn, pool)?;
     store::update_deployed_pool(&mut conn, coin_id, sol_pool.lp_token)?;
@@ -104,8 +203,10 @@ pub(crate) async fn deploy_coin(
 
     let mut conn = state.db_conn()?;
     store::graduate_coin(&mut conn, coin_id)?;
-    client.deploy_graduated(coin_id as u32).await?;
-    Ok((StatusCode::OK, Json(format!("Deployed coinId={}", coin_id))).into_response())
+    // the listener's keeper sends the tx, retrying until the coin is deployed
+    store::enqueue_deployment(&mut conn, coin_id)?;
+    Ok((StatusCode::ACCEPTED, Json(format!("Queued deployment for coinId={}", coin_id)))
+        .into_response())
 }
 
 pub(crate) async fn get_all_coins_handler(
@@ -118,11 +219,14 @@ pub(crate) async fn get_all_coins_handler(
     Ok(Json(coin_list).into_response())
 }
 
-/// Handler for POST /coin/create
+/// Handler for POST /coin/create. Must be signed by the coin's creator
 pub(crate) async fn create_coin_handler(
     State(state): State<AppState>,
+    headers: HeaderMap,
     Json(payload): Json<models::NewCoin>,
 ) -> Result<impl IntoResponse, PumpError> {
+    let proof = OwnershipProof::from_headers(&headers)?;
+    ownership::verify(&state, payload.id, CoinWrite::Metadata(&payload), proof).await?;
     let mut conn = state.db_conn()?;
     let coin = store::upsert_unverified_coin(&mut conn, payload)?;
     Ok(Json(CoinResponse { coin }).into_response())
@@ -131,7 +235,7 @@ pub(crate) async fn create_coin_handler(
 async fn parse_upload(mut multipart: Multipart) -> Result<Vec<u8>, PumpError> {
     // Look for the file field in the multipart form.
     let mut file_bytes: Option<Vec<u8>> = None;
-    while let Some(field) = multipart.next_field().await.unwrap() {
+    while let Some(field) = multipart.next_field().await? {
         // Check if this field is a file (it will have a filename).
         if field.file_name().is_some() {
             // Read all bytes from the field.
@@ -152,14 +256,20 @@ async fn parse_upload(mut multipart: Multipart) -> Result<Vec<u8>, PumpError> {
     }
 }
 
+/// Handler for POST /coin/:id/upload. Must be signed by the coin's creator
 pub(crate) async fn upload_file(
     // Extract the S3 client from shared state.
     State(state): State<AppState>,
     // Extract the coin id from the URL path.
-    Path(coin_id): Path<String>,
+    Path(coin_id): Path<i64>,
+    headers: HeaderMap,
     // Extract the multipart form data.
     multipart: Multipart,
 ) -> Result<impl IntoResponse, PumpError> {
+    let proof = OwnershipProof::from_headers(&headers)?;
+    let file_bytes = parse_upload(multipart).await?;
+    ownership::verify(&state, coin_id, CoinWrite::Upload(&file_bytes), proof).await?;
+
     let s3_client = state.s3_client;
 
     // Prepare S3 upload parameters.
@@ -167,8 +277,6 @@ pub(crate) async fn upload_file(
     // The key will be under the "pump" folder with the filename equal to the coin id.
     let key = format!("pump/{}/{}", state.pump_client.chain_id, coin_id);
 
-    let file_bytes = parse_upload(multipart).await?;
-
     // Upload the file to S3 with the public-read ACL.
     let upload_result = s3_client
         .put_object()
@@ -0,0 +1,322 @@
+use axum::{
+    extract::{ConnectInfo, State},
+    http::{HeaderMap, Request},
+    middleware::Next,
+    response::Response,
+};
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use pump::error::PumpError;
+
+const X_FORWARDED_FOR: &str = "x-forwarded-for";
+
+const DEFAULT_READ_PER_MIN: u64 = 600;
+const DEFAULT_WRITE_PER_MIN: u64 = 30;
+const DEFAULT_ADMIN_PER_MIN: u64 = 60;
+const DEFAULT_WS_PER_MIN: u64 = 30;
+const DEFAULT_WS_MAX_CONNECTIONS_PER_IP: usize = 8;
+/// The frontend caps images at 1MB; leave room for the multipart framing
+const DEFAULT_MAX_UPLOAD_BYTES: usize = 2 * 1024 * 1024;
+const DEFAULT_MAX_CREATE_BYTES: usize = 16 * 1024;
+
+/// Forget clients once we're tracking this many and their buckets have refilled
+const PRUNE_THRESHOLD: usize = 10_000;
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Token bucket per client IP. Each request takes a token;
+/// tokens refill at `per_min` a minute, up to `burst`
+pub(crate) struct RateLimiter {
+    name: &'static str,
+    burst: f64,
+    per_sec: f64,
+    buckets: Mutex<HashMap<IpAddr, Bucket>>,
+}
+
+impl RateLimiter {
+    /// Reads `RATE_LIMIT_<GROUP>` (requests a minute, 0 turns it off)
+    /// and `RATE_LIMIT_<GROUP>_BURST` (defaults to a minute's worth)
+    fn from_env(name: &'static str, default_per_min: u64) -> Option<Arc<RateLimiter>> {
+        let key = format!("RATE_LIMIT_{}", name.to_uppercase());
+        let per_min = env_u64(&key).unwrap_or(default_per_min);
+        if per_min == 0 {
+            log::warn!("Rate limiting is off for {} routes", name);
+            return None;
+        }
+        let burst = env_u64(&format!("{}_BURST", key)).unwrap_or(per_min);
+        Some(Arc::new(RateLimiter::new(name, per_min, burst)))
+    }
+
+    fn new(name: &'static str, per_min: u64, burst: u64) -> RateLimiter {
+        RateLimiter {
+            name,
+            burst: burst.max(1) as f64,
+            per_sec: per_min as f64 / 60.0,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Takes a token for `ip` at `now`, or returns how long until one is available
+    fn check(&self, ip: IpAddr, now: Instant) -> Result<(), Duration> {
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() >= PRUNE_THRESHOLD {
+            self.prune(&mut buckets, now);
+        }
+        let bucket = buckets.entry(ip).or_insert(Bucket { tokens: self.burst, updated: now });
+        bucket.tokens = self.refill(bucket, now);
+        bucket.updated = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return Ok(());
+        }
+        Err(Duration::from_secs_f64((1.0 - bucket.tokens) / self.per_sec))
+    }
+
+    fn refill(&self, bucket: &Bucket, now: Instant) -> f64 {
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+        (bucket.tokens + elapsed * self.per_sec).min(self.burst)
+    }
+
+    /// Full buckets are the same as no bucket
+    fn prune(&self, buckets: &mut HashMap<IpAddr, Bucket>, now: Instant) {
+        buckets.retain(|_, bucket| self.refill(bucket, now) < self.burst);
+    }
+}
+
+/// Rate limits for each group of routes, and the request size caps
+pub(crate) struct Limits {
+    /// public GETs
+    pub read: Option<Arc<RateLimiter>>,
+    /// coin creation and image uploads
+    pub write: Option<Arc<RateLimiter>>,
+    /// routes that need an API key
+    pub admin: Option<Arc<RateLimiter>>,
+    /// websocket handshakes
+    pub ws: Option<Arc<RateLimiter>>,
+    pub max_upload_bytes: usize,
+    pub max_create_bytes: usize,
+}
+
+impl Limits {
+    pub fn from_env() -> Limits {
+        Limits {
+            read: RateLimiter::from_env("read", DEFAULT_READ_PER_MIN),
+            write: RateLimiter::from_env("write", DEFAULT_WRITE_PER_MIN),
+            admin: RateLimiter::from_env("admin", DEFAULT_ADMIN_PER_MIN),
+            ws: RateLimiter::from_env("ws", DEFAULT_WS_PER_MIN),
+            max_upload_bytes: env_u64("MAX_UPLOAD_BYTES")
+                .map(|bytes| bytes as usize)
+                .unwrap_or(DEFAULT_MAX_UPLOAD_BYTES),
+            max_create_bytes: env_u64("MAX_CREATE_BYTES")
+                .map(|bytes| bytes as usize)
+                .unwrap_or(DEFAULT_MAX_CREATE_BYTES),
+        }
+    }
+}
+
+/// Caps how many websockets each IP can have open at once
+pub(crate) struct ConnectionLimiter {
+    max: usize,
+    open: Mutex<HashMap<IpAddr, usize>>,
+}
+
+/// Holds one of the IP's connections until dropped
+pub(crate) struct ConnectionGuard {
+    limiter: Arc<ConnectionLimiter>,
+    ip: IpAddr,
+}
+
+impl ConnectionLimiter {
+    /// Reads the cap from `WS_MAX_CONNECTIONS_PER_IP` (0 turns it off)
+    pub fn from_env() -> ConnectionLimiter {
+        let max = env_u64("WS_MAX_CONNECTIONS_PER_IP")
+            .map(|max| max as usize)
+            .unwrap_or(DEFAULT_WS_MAX_CONNECTIONS_PER_IP);
+        ConnectionLimiter::new(max)
+    }
+
+    fn new(max: usize) -> ConnectionLimiter {
+        ConnectionLimiter { max, open: Mutex::new(HashMap::new()) }
+    }
+
+    pub fn acquire(self: &Arc<Self>, ip: IpAddr) -> Option<ConnectionGuard> {
+        let mut open = self.open.lock().unwrap();
+        let count = open.entry(ip).or_insert(0);
+        if self.max > 0 && *count >= self.max {
+            return None;
+        }
+        *count += 1;
+        Some(ConnectionGuard { limiter: self.clone(), ip })
+    }
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        let mut open = self.limiter.open.lock().unwrap();
+        if let Some(count) = open.get_mut(&self.ip) {
+            *count -= 1;
+            if *count == 0 {
+                open.remove(&self.ip);
+            }
+        }
+    }
+}
+
+/// The client's address. Behind nginx every connection comes from localhost,
+/// so trust the last `X-Forwarded-For` hop, which nginx appends with the address it saw
+pub(crate) fn client_ip(headers: &HeaderMap, peer: SocketAddr) -> IpAddr {
+    if !peer.ip().is_loopback() {
+        return peer.ip();
+    }
+    headers
+        .get_all(X_FORWARDED_FOR)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .last()
+        .and_then(|hop| hop.trim().parse::<IpAddr>().ok())
+        .unwrap_or(peer.ip())
+}
+
+/// Middleware that rejects clients who've used up their tokens with a 429 and `Retry-After`
+pub(crate) async fn rate_limit<B>(
+    State(limiter): State<Arc<RateLimiter>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Result<Response, PumpError> {
+    let ip = client_ip(request.headers(), peer);
+    if let Err(retry_after) = limiter.check(ip, Instant::now()) {
+        log::debug!("Rate limited {} on {} routes ({})", ip, limiter.name, request.uri());
+        // round up so clients that wait exactly that long get through
+        return Err(PumpError::RateLimited(retry_after.as_secs() + 1));
+    }
+    Ok(next.run(request).await)
+}
+
+fn env_u64(key: &str) -> Option<u64> {
+    std::env::var(key).ok().and_then(|value| value.parse::<u64>().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use axum::http::HeaderValue;
+
+    fn ip(last: u8) -> IpAddr {
+        IpAddr::from([10, 0, 0, last])
+    }
+
+    #[test]
+    fn test_burst_then_retry_after() {
+        // a token a second, up to 3
+        let limiter = RateLimiter::new("test", 60, 3);
+        let now = Instant::now();
+        for _ in 0..3 {
+            assert!(limiter.check(ip(1), now).is_ok());
+        }
+        let retry_after = limiter.check(ip(1), now).unwrap_err();
+        assert!(retry_after > Duration::from_millis(999) && retry_after <= Duration::from_secs(1));
+        // other clients have their own bucket
+        assert!(limiter.check(ip(2), now).is_ok());
+    }
+
+    #[test]
+    fn test_refill() {
+        let limiter = RateLimiter::new("test", 60, 3);
+        let start = Instant::now();
+        for _ in 0..3 {
+            assert!(limiter.check(ip(1), start).is_ok());
+        }
+        let later = start + Duration::from_millis(1_500);
+        assert!(limiter.check(ip(1), later).is_ok());
+        // half a token left, so half a second to wait
+        let retry_after = limiter.check(ip(1), later).unwrap_err();
+        assert!(
+            retry_after > Duration::from_millis(499) && retry_after <= Duration::from_millis(500)
+        );
+
+        // refills stop at the burst
+        let much_later = start + Duration::from_secs(3_600);
+        for _ in 0..3 {
+            assert!(limiter.check(ip(1), much_later).is_ok());
+        }
+        assert!(limiter.check(ip(1), much_later).is_err());
+    }
+
+    #[test]
+    fn test_prune_forgets_full_buckets() {
+        let limiter = RateLimiter::new("test", 60, 2);
+        let start = Instant::now();
+        let mut buckets = HashMap::new();
+        buckets.insert(ip(1), Bucket { tokens: 0.0, updated: start });
+        buckets.insert(ip(2), Bucket { tokens: 2.0, updated: start });
+        limiter.prune(&mut buckets, start + Duration::from_secs(1));
+        assert_eq!(buckets.keys().collect::<Vec<_>>(), vec![&ip(1)]);
+        limiter.prune(&mut buckets, start + Duration::from_secs(2));
+        assert!(buckets.is_empty());
+    }
+
+    #[test]
+    fn test_disabled_limit() {
+        std::env::set_var("RATE_LIMIT_TEST_OFF", "0");
+        assert!(RateLimiter::from_env("test_off", 60).is_none());
+        std::env::set_var("RATE_LIMIT_TEST_ON", "5");
+        std::env::set_var("RATE_LIMIT_TEST_ON_BURST", "2");
+        let limiter = RateLimiter::from_env("test_on", 60).unwrap();
+        assert_eq!((limiter.burst, limiter.per_sec), (2.0, 5.0 / 60.0));
+    }
+
+    #[test]
+    fn test_connection_cap() {
+        let limiter = Arc::new(ConnectionLimiter::new(2));
+        let first = limiter.acquire(ip(1)).unwrap();
+        let _second = limiter.acquire(ip(1)).unwrap();
+        assert!(limiter.acquire(ip(1)).is_none());
+        assert!(limiter.acquire(ip(2)).is_some());
+
+        drop(first);
+        let _third = limiter.acquire(ip(1)).unwrap();
+        assert!(limiter.acquire(ip(1)).is_none());
+    }
+
+    #[test]
+    fn test_guards_release_their_ip() {
+        let limiter = Arc::new(ConnectionLimiter::new(1));
+        drop(limiter.acquire(ip(1)).unwrap());
+        assert!(limiter.open.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_unlimited_connections() {
+        let limiter = Arc::new(ConnectionLimiter::new(0));
+        let guards: Vec<_> = (0..100).map(|_| limiter.acquire(ip(1)).unwrap()).collect();
+        assert_eq!(guards.len(), 100);
+    }
+
+    #[test]
+    fn test_client_ip() {
+        let local = SocketAddr::from(([127, 0, 0, 1], 50_000));
+        let remote = SocketAddr::from(([203, 0, 113, 7], 50_000));
+        let mut headers = HeaderMap::new();
+        assert_eq!(client_ip(&headers, local), local.ip());
+
+        // nginx appends the address it saw, after anything the client sent
+        headers.insert(X_FORWARDED_FOR, HeaderValue::from_static("1.1.1.1, 198.51.100.2"));
+        assert_eq!(client_ip(&headers, local), IpAddr::from([198, 51, 100, 2]));
+        // only proxies on this host are trusted
+        assert_eq!(client_ip(&headers, remote), remote.ip());
+
+        headers.insert(X_FORWARDED_FOR, HeaderValue::from_static("garbage"));
+        assert_eq!(client_ip(&headers, local), local.ip());
+    }
+}
@@ -1,17 +1,26 @@
 // src/main.rs
+mod auth;
+mod channel;
+mod history;
 mod http;
+mod limit;
+mod notify;
+mod ownership;
+mod pubsub;
 mod sock;
 mod state;
 mod ws;
 
-use std::net::SocketAddr;
+use std::{net::SocketAddr, sync::Arc};
 
 use axum::{
+    extract::DefaultBodyLimit,
     http::{HeaderValue, Method},
+    middleware,
     routing::{get, post},
     Router,
 };
-use pump::get_workspace_root;
+use pump::{db::models::ApiScope, get_workspace_root, UpdateTransport};
 use tower_http::cors::{Any, CorsLayer};
 
 use crate::state::AppState;
@@ -23,7 +32,10 @@ async fn main() {
     env_logger::init();
 
     let app_state = AppState::new().await.expect("Failed to create app state");
-    sock::setup_unix_socket(app_state.ws.clone());
+    match UpdateTransport::from_env() {
+        UpdateTransport::Socket => sock::setup_unix_socket(app_state.ws.clone()),
+        UpdateTransport::Postgres => notify::setup_pg_listener(app_state.ws.clone()),
+    }
 
     // CORS for local dev with vite frontend
     let origin = "http://localhost:5173".parse::<HeaderValue>().unwrap();
@@ -32,32 +44,77 @@ async fn main() {
         .allow_methods(vec![Method::GET, Method::POST])
         .allow_headers(Any);
 
+    // Per-IP rate limits for each group of routes; a group without a limiter is unlimited
+    let limits = limit::Limits::from_env();
+    let rate_limited =
+        |limiter: &Option<Arc<limit::RateLimiter>>, router: Router<AppState>| match limiter {
+            Some(limiter) => router
+                .route_layer(middleware::from_fn_with_state(limiter.clone(), limit::rate_limit)),
+            None => router,
+        };
+
     // Define sub-router to handle /coin/:id routes
     let coin_routes = Router::new()
         .route("/", get(http::get_coin_handler)) // GET /coin/:id/snippet
+        .route("/progress", get(http::get_coin_progress));
+
+    let coin_upload_routes = Router::new()
         .route("/upload", post(http::upload_file)) // POST /coin/:id/upload
-        .route("/verify", post(http::verify_coin_handler))
-        .route("/sync", post(http::sync_coin))
-        .route("/deploy", post(http::deploy_coin));
+        .layer(DefaultBodyLimit::max(limits.max_upload_bytes));
+
+    // Privileged /coin/:id routes need an API key with the route's scope
+    let require =
+        |scope| middleware::from_fn_with_state(auth::scope(&app_state, scope), auth::require_scope);
+    let coin_admin_routes = Router::new()
+        .route("/verify", post(http::verify_coin_handler).route_layer(require(ApiScope::Verify)))
+        .route("/sync", post(http::sync_coin).route_layer(require(ApiScope::Sync)))
+        .route("/deploy", post(http::deploy_coin).route_layer(require(ApiScope::Deploy)));
 
     let coins_routes = Router::new()
         .route("/", get(http::get_all_coins_handler)) // GET /coins
-        .route("/create", post(http::create_coin_handler)) // POST /coins/create
         .route("/address/:address", get(http::get_coin_by_address_handler));
 
-    let pool_routes = Router::new().route("/prices", get(http::get_pool_prices));
+    let coins_create_routes = Router::new()
+        .route("/create", post(http::create_coin_handler)) // POST /coins/create
+        .layer(DefaultBodyLimit::max(limits.max_create_bytes));
+
+    let pool_routes = Router::new()
+        .route("/prices", get(http::get_pool_prices))
+        .route("/trades", get(http::get_pool_trades));
+
+    let trader_routes = Router::new().route("/trades", get(http::get_trader_trades));
+
+    let root_routes = Router::new()
+        .route("/hall-of-fame", get(http::get_hall_of_fame))
+        .route("/config", get(http::get_pump_config));
+
+    let ws_routes = Router::new().route("/ws", get(ws::ws_handler));
 
     // Define the main router.
     let app = Router::new()
-        .route("/ws", get(ws::ws_handler))
-        .route("/hall-of-fame", get(http::get_hall_of_fame))
-        .nest("/coin/:id", coin_routes)
-        .nest("/coins", coins_routes)
-        .nest("/pool/:pool", pool_routes)
+        .merge(rate_limited(&limits.ws, ws_routes))
+        .merge(rate_limited(&limits.read, root_routes))
+        .nest(
+            "/coin/:id",
+            rate_limited(&limits.read, coin_routes)
+                .merge(rate_limited(&limits.write, coin_upload_routes))
+                .merge(rate_limited(&limits.admin, coin_admin_routes)),
+        )
+        .nest(
+            "/coins",
+            rate_limited(&limits.read, coins_routes)
+                .merge(rate_limited(&limits.write, coins_create_routes)),
+        )
+        .nest("/pool/:pool", rate_limited(&limits.read, pool_routes))
+        .nest("/trader/:address", rate_limited(&limits.read, trader_routes))
         .with_state(app_state)
         .layer(cors);
 
     let addr = SocketAddr::from(([127, 0, 0, 1], 3000));
     println!("Listening on http://{}", addr);
-    axum::Server::bind(&addr).serve(app.into_make_service()).await.unwrap();
+    // rate limits need the client's address
+    axum::Server::bind(&addr)
+        .serve(app.into_make_service_with_connect_info::<SocketAddr>())
+        .await
+        .unwrap();
 }
@@ -0,0 +1,109 @@
+use futures::{channel::mpsc, stream, StreamExt};
+use std::time::Duration;
+use tokio_postgres::{AsyncMessage, Client, NoTls};
+
+use pump::{StoredNotification, NOTIFY_CHANNEL};
+
+use crate::{channel::Broadcast, state::WsState};
+
+const RECONNECT_BASE_DELAY: Duration = Duration::from_millis(500);
+const RECONNECT_MAX_DELAY: Duration = Duration::from_secs(30);
+
+/// Receives listener updates over Postgres LISTEN/NOTIFY instead of the unix socket,
+/// so the listener and any number of servers can run on different hosts
+pub(crate) fn setup_pg_listener(state: WsState) {
+    spawn_pg_listener(NOTIFY_CHANNEL, state, WsState::publish);
+}
+
+/// Calls `handle` with every message notified on the channel, reconnecting if the connection drops
+pub(crate) fn spawn_pg_listener(
+    channel: &'static str,
+    state: WsState,
+    handle: fn(&WsState, Broadcast),
+) {
+    let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set in .env");
+    tokio::spawn(async move {
+        let mut delay = RECONNECT_BASE_DELAY;
+        loop {
+            match listen(&database_url, channel, &state, handle, &mut delay).await {
+                Ok(()) => log::warn!("Postgres notification stream ended, reconnecting"),
+                Err(e) => log::error!("Error listening for Postgres notifications: {:?}", e),
+            }
+            // anything sent while we were disconnected is lost, clients will need to resync
+            tokio::time::sleep(delay).await;
+            delay = (delay * 2).min(RECONNECT_MAX_DELAY);
+        }
+    });
+}
+
+async fn listen(
+    database_url: &str,
+    channel: &str,
+    state: &WsState,
+    handle: fn(&WsState, Broadcast),
+    delay: &mut Duration,
+) -> Result<(), tokio_postgres::Error> {
+    let (client, mut connection) = tokio_postgres::connect(database_url, NoTls).await?;
+
+    // the connection has to be polled for the client's queries to complete
+    let (tx, mut rx) = mpsc::unbounded();
+    let messages = stream::poll_fn(move |cx| connection.poll_message(cx));
+    tokio::spawn(messages.map(Ok).forward(tx));
+
+    client.batch_execute(&format!("LISTEN {}", channel)).await?;
+    log::info!("Listening for Postgres notifications on {}", channel);
+    *delay = RECONNECT_BASE_DELAY;
+
+    while let Some(message) = rx.next().await {
+        match message? {
+            AsyncMessage::Notification(notification) => {
+                let payload = match stored_id(notification.payload()) {
+                    Some(id) => match fetch_stored(&client, id).await? {
+                        Some(payload) => payload,
+                        None => {
+                            // clients will see the gap and resync
+                            log::error!("Stored notification {} no longer exists", id);
+                            continue;
+                        }
+                    },
+                    None => notification.payload().to_string(),
+                };
+                match Broadcast::parse(payload.as_bytes()) {
+                    Ok(broadcast) => {
+                        log::info!("Received from Postgres: {}", broadcast.text);
+                        handle(state, broadcast);
+                    }
+                    Err(e) => {
+                        log::error!("Rejecting malformed notification: {:?}: {}", e, payload);
+                    }
+                }
+            }
+            AsyncMessage::Notice(notice) => log::info!("Postgres notice: {}", notice),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// The `notify_payloads` row holding the message, if it was too big to send in the notification
+fn stored_id(payload: &str) -> Option<i64> {
+    serde_json::from_str::<StoredNotification>(payload).ok().map(|stored| stored.stored_id)
+}
+
+async fn fetch_stored(client: &Client, id: i64) -> Result<Option<String>, tokio_postgres::Error> {
+    let row = client.query_opt("SELECT payload FROM notify_payloads WHERE id = $1", &[&id]).await?;
+    Ok(row.map(|row| row.get(0)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stored_id() {
+        assert_eq!(stored_id(r#"{"storedId":42}"#), Some(42));
+        let message = r#"{"seq":7,"type":"graduatedCoin","data":{"id":42},"provisional":false}"#;
+        assert_eq!(stored_id(message), None);
+        assert!(Broadcast::parse(message.as_bytes()).is_ok());
+    }
+}
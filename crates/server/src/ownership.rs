@@ -0,0 +1,322 @@
+use alloy_primitives::{keccak256, Address, Signature};
+use axum::http::HeaderMap;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use std::str::FromStr;
+
+use pump::{
+    db::{models::NewCoin, store},
+    error::PumpError,
+};
+
+use crate::AppState;
+
+const SIGNATURE_HEADER: &str = "x-signature";
+const NONCE_HEADER: &str = "x-signature-nonce";
+const EXPIRES_HEADER: &str = "x-signature-expires";
+/// signatures can't be valid for longer than this, so we only keep nonces this long
+const MAX_SIGNATURE_TTL_SECS: i64 = 15 * 60;
+const MAX_NONCE_LEN: usize = 128;
+
+/// A creator's EIP-191 signature over a write to their coin, sent in headers
+pub(crate) struct OwnershipProof {
+    signature: Signature,
+    nonce: String,
+    /// unix seconds
+    expires_at: i64,
+}
+
+/// The write a proof covers
+pub(crate) enum CoinWrite<'a> {
+    Metadata(&'a NewCoin),
+    /// the uploaded image's bytes
+    Upload(&'a [u8]),
+}
+
+fn header<'a>(headers: &'a HeaderMap, name: &str) -> Result<&'a str, PumpError> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .ok_or_else(|| PumpError::InvalidSignature(format!("missing {} header", name)))
+}
+
+impl OwnershipProof {
+    /// Reads `X-Signature`, `X-Signature-Nonce` and `X-Signature-Expires`
+    pub fn from_headers(headers: &HeaderMap) -> Result<OwnershipProof, PumpError> {
+        let signature = Signature::from_str(header(headers, SIGNATURE_HEADER)?)
+            .map_err(|_| PumpError::InvalidSignature("malformed signature".to_string()))?;
+        let nonce = header(headers, NONCE_HEADER)?.to_string();
+        if nonce.is_empty()
+            || nonce.len() > MAX_NONCE_LEN
+            || !nonce.chars().all(|c| c.is_ascii_alphanumeric())
+        {
+            return Err(PumpError::InvalidSignature("malformed nonce".to_string()));
+        }
+        let expires_at = header(headers, EXPIRES_HEADER)?
+            .parse::<i64>()
+            .map_err(|_| PumpError::InvalidSignature("malformed expiry".to_string()))?;
+        Ok(OwnershipProof { signature, nonce, expires_at })
+    }
+}
+
+/// The message the creator signs. The frontend builds the same one in `api/ownership.ts`
+pub(crate) fn message(
+    chain_id: u64,
+    coin_id: i64,
+    write: &CoinWrite,
+    nonce: &str,
+    expires_at: i64,
+) -> String {
+    let mut lines = vec![format!("Update coin {} on Moonhatch", coin_id)];
+    lines.push(format!("Chain ID: {}", chain_id));
+    match write {
+        CoinWrite::Metadata(coin) => {
+            let field = |value: &Option<String>| value.clone().unwrap_or_default();
+            lines.push("Action: metadata".to_string());
+            lines.push(format!("Description: {}", field(&coin.description)));
+            lines.push(format!("Image URL: {}", field(&coin.image_url)));
+            lines.push(format!("Website: {}", field(&coin.website)));
+            lines.push(format!("Telegram: {}", field(&coin.telegram)));
+            lines.push(format!("Twitter: {}", field(&coin.twitter)));
+        }
+        CoinWrite::Upload(bytes) => {
+            lines.push("Action: upload".to_string());
+            lines.push(format!("Image: {}", keccak256(bytes)));
+        }
+    }
+    lines.push(format!("Nonce: {}", nonce));
+    lines.push(format!("Expires At: {}", expires_at));
+    lines.join("\n")
+}
+
+/// The coin's creator. Only trusts the DB once the listener has verified the coin,
+/// because unverified rows come from the client
+async fn creator(state: &AppState, coin_id: i64) -> Result<Address, PumpError> {
+    let mut conn = state.db_conn()?;
+    if let Ok(coin) = store::get_coin(&mut conn, coin_id) {
+        if coin.verified {
+            return Address::from_str(coin.creator.trim()).map_err(|_| PumpError::InvalidAddress);
+        }
+    }
+    Ok(state.pump_client.get_coin(coin_id as u32).await?.creator)
+}
+
+/// Checks that the coin's creator signed this write, and that the signature is fresh
+pub(crate) async fn verify(
+    state: &AppState,
+    coin_id: i64,
+    write: CoinWrite<'_>,
+    proof: OwnershipProof,
+) -> Result<Address, PumpError> {
+    let chain_id = state.pump_client.chain_id;
+    let creator = creator(state, coin_id).await?;
+    let mut conn = state.db_conn()?;
+    verify_proof(
+        chain_id,
+        coin_id,
+        &write,
+        proof,
+        creator,
+        Utc::now().timestamp(),
+        |signer, nonce, expires_at| {
+            store::use_signature_nonce(&mut conn, signer, nonce, expires_at)
+        },
+    )
+}
+
+/// `verify` without the DB or chain: `use_nonce` records the signer's nonce,
+/// returning false if it was already used
+fn verify_proof(
+    chain_id: u64,
+    coin_id: i64,
+    write: &CoinWrite,
+    proof: OwnershipProof,
+    creator: Address,
+    now: i64,
+    use_nonce: impl FnOnce(Address, &str, NaiveDateTime) -> Result<bool, PumpError>,
+) -> Result<Address, PumpError> {
+    if proof.expires_at <= now {
+        return Err(PumpError::InvalidSignature("signature expired".to_string()));
+    }
+    if proof.expires_at > now + MAX_SIGNATURE_TTL_SECS {
+        return Err(PumpError::InvalidSignature(format!(
+            "signature must expire within {} seconds",
+            MAX_SIGNATURE_TTL_SECS
+        )));
+    }
+
+    let message = message(chain_id, coin_id, write, &proof.nonce, proof.expires_at);
+    let signer = proof
+        .signature
+        .recover_address_from_msg(message.as_bytes())
+        .map_err(|_| PumpError::InvalidSignature("could not recover signer".to_string()))?;
+    if signer != creator {
+        log::warn!("{} signed a write to coin {} they didn't create", signer, coin_id);
+        return Err(PumpError::NotCoinCreator(coin_id));
+    }
+
+    // only burn the nonce once everything else checks out
+    let expires_at = DateTime::from_timestamp(proof.expires_at, 0)
+        .ok_or_else(|| PumpError::InvalidSignature("malformed expiry".to_string()))?
+        .naive_utc();
+    if !use_nonce(signer, &proof.nonce, expires_at)? {
+        return Err(PumpError::InvalidSignature("nonce already used".to_string()));
+    }
+    Ok(signer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use alloy_signer::SignerSync;
+    use alloy_signer_local::PrivateKeySigner;
+    use bigdecimal::BigDecimal;
+    use std::collections::HashSet;
+
+    const CHAIN_ID: u64 = 31337;
+    const NOW: i64 = 1_700_000_000;
+    /// anvil's first two accounts
+    const CREATOR_KEY: &str = "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+    const OTHER_KEY: &str = "0x59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d";
+
+    fn signer(key: &str) -> PrivateKeySigner {
+        PrivateKeySigner::from_str(key).unwrap()
+    }
+
+    fn metadata() -> NewCoin {
+        NewCoin {
+            id: 7,
+            name: "Moon".to_string(),
+            symbol: "MOON".to_string(),
+            supply: BigDecimal::from(1_000_000),
+            decimals: 18,
+            contract_address: Address::ZERO.to_string(),
+            creator: signer(CREATOR_KEY).address().to_string(),
+            description: Some("to the moon".to_string()),
+            image_url: None,
+            twitter: None,
+            website: Some("https://example.com".to_string()),
+            telegram: None,
+        }
+    }
+
+    fn sign(key: &str, coin_id: i64, write: &CoinWrite, nonce: &str) -> OwnershipProof {
+        let expires_at = NOW + 300;
+        let message = message(CHAIN_ID, coin_id, write, nonce, expires_at);
+        let signed = signer(key).sign_message_sync(message.as_bytes()).unwrap();
+        // the same 65 bytes a wallet sends in X-Signature
+        let signature = Signature::try_from(&signed.as_bytes()[..]).unwrap();
+        OwnershipProof { signature, nonce: nonce.to_string(), expires_at }
+    }
+
+    /// Verifies against an in-memory nonce table
+    fn check(
+        proof: OwnershipProof,
+        write: &CoinWrite,
+        used: &mut HashSet<(Address, String)>,
+    ) -> Result<Address, PumpError> {
+        let creator = signer(CREATOR_KEY).address();
+        verify_proof(CHAIN_ID, 7, write, proof, creator, NOW, |signer, nonce, _| {
+            Ok(used.insert((signer, nonce.to_string())))
+        })
+    }
+
+    #[test]
+    fn test_message_format() {
+        // `ownershipMessage` in packages/web/src/api/ownership.ts builds the same strings
+        let coin = metadata();
+        let expected = "Update coin 7 on Moonhatch
+Chain ID: 31337
+Action: metadata
+Description: to the moon
+Image URL: 
+Website: https://example.com
+Telegram: 
+Twitter: 
+Nonce: abc123
+Expires At: 1700000300";
+        let metadata = CoinWrite::Metadata(&coin);
+        assert_eq!(message(CHAIN_ID, 7, &metadata, "abc123", NOW + 300), expected);
+
+        let expected = format!(
+            "Update coin 7 on Moonhatch
+Chain ID: 31337
+Action: upload
+Image: {}
+Nonce: abc123
+Expires At: 1700000300",
+            keccak256(b"image")
+        );
+        let upload = CoinWrite::Upload(b"image");
+        assert_eq!(message(CHAIN_ID, 7, &upload, "abc123", NOW + 300), expected);
+    }
+
+    #[test]
+    fn test_keys() {
+        let creator = Address::from_str("0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266").unwrap();
+        assert_eq!(signer(CREATOR_KEY).address(), creator);
+        let other = Address::from_str("0x70997970C51812dc3A010C7d01b50e0d17dc79C8").unwrap();
+        assert_eq!(signer(OTHER_KEY).address(), other);
+    }
+
+    #[test]
+    fn test_creator_signature() {
+        let coin = metadata();
+        let write = CoinWrite::Metadata(&coin);
+        let mut used = HashSet::new();
+        let proof = sign(CREATOR_KEY, 7, &write, "abc123");
+        assert_eq!(check(proof, &write, &mut used).unwrap(), signer(CREATOR_KEY).address());
+    }
+
+    #[test]
+    fn test_wrong_signer() {
+        let write = CoinWrite::Upload(b"image");
+        let mut used = HashSet::new();
+        let proof = sign(OTHER_KEY, 7, &write, "abc123");
+        assert!(matches!(check(proof, &write, &mut used), Err(PumpError::NotCoinCreator(7))));
+        // a rejected proof doesn't burn the nonce
+        assert!(used.is_empty());
+    }
+
+    #[test]
+    fn test_signature_covers_write() {
+        let write = CoinWrite::Upload(b"image");
+        let mut used = HashSet::new();
+        let proof = sign(CREATOR_KEY, 7, &write, "abc123");
+        // recovers some other address, so it's not the creator
+        let other = CoinWrite::Upload(b"other image");
+        assert!(matches!(check(proof, &other, &mut used), Err(PumpError::NotCoinCreator(7))));
+    }
+
+    #[test]
+    fn test_reused_nonce() {
+        let write = CoinWrite::Upload(b"image");
+        let mut used = HashSet::new();
+        assert!(check(sign(CREATOR_KEY, 7, &write, "abc123"), &write, &mut used).is_ok());
+        let replayed = check(sign(CREATOR_KEY, 7, &write, "abc123"), &write, &mut used);
+        assert!(matches!(replayed, Err(PumpError::InvalidSignature(_))));
+        assert!(check(sign(CREATOR_KEY, 7, &write, "def456"), &write, &mut used).is_ok());
+    }
+
+    #[test]
+    fn test_expiry() {
+        let write = CoinWrite::Upload(b"image");
+        let creator = signer(CREATOR_KEY).address();
+        let proof = sign(CREATOR_KEY, 7, &write, "abc123");
+        let expired =
+            verify_proof(CHAIN_ID, 7, &write, proof, creator, NOW + 300, |_, _, _| Ok(true));
+        assert!(matches!(expired, Err(PumpError::InvalidSignature(_))));
+
+        let proof = sign(CREATOR_KEY, 7, &write, "abc123");
+        let too_long = verify_proof(
+            CHAIN_ID,
+            7,
+            &write,
+            proof,
+            creator,
+            NOW + 300 - MAX_SIGNATURE_TTL_SECS - 1,
+            |_, _, _| Ok(true),
+        );
+        assert!(matches!(too_long, Err(PumpError::InvalidSignature(_))));
+    }
+}
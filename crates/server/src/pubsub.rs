@@ -0,0 +1,266 @@
+use futures::StreamExt;
+use redis::aio::MultiplexedConnection;
+use std::time::Duration;
+use tokio::sync::mpsc::UnboundedReceiver;
+
+use pump::db::{
+    pool::{connect, PgPool},
+    store,
+};
+
+use crate::{channel::Broadcast, notify::spawn_pg_listener, state::WsState};
+
+/// Channel replicas share listener updates on, for both Postgres and Redis
+const PUBSUB_CHANNEL: &str = "ws_updates";
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+const RECONNECT_BASE_DELAY: Duration = Duration::from_millis(500);
+const RECONNECT_MAX_DELAY: Duration = Duration::from_secs(30);
+
+/// Shares the listener's updates between server replicas,
+/// so clients get every update no matter which replica received it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum PubSubBackend {
+    /// a single server, updates are broadcast straight to its clients
+    Memory,
+    /// NOTIFY and LISTEN on `PUBSUB_CHANNEL`
+    Postgres,
+    /// PUBLISH and SUBSCRIBE on `PUBSUB_CHANNEL` on the server at this url
+    Redis(String),
+}
+
+impl PubSubBackend {
+    /// Reads `WS_PUBSUB`, which is `memory` (the default), `postgres` or `redis`.
+    /// Redis connects to `REDIS_URL`
+    pub fn from_env() -> PubSubBackend {
+        match std::env::var("WS_PUBSUB").as_deref() {
+            Ok("memory") | Err(_) => PubSubBackend::Memory,
+            Ok("postgres") => PubSubBackend::Postgres,
+            Ok("redis") => {
+                let url = redis_url();
+                match redis::Client::open(url.as_str()) {
+                    Ok(_) => PubSubBackend::Redis(url),
+                    Err(e) => {
+                        log::error!("Invalid REDIS_URL, using memory pub/sub: {:?}", e);
+                        PubSubBackend::Memory
+                    }
+                }
+            }
+            Ok(other) => {
+                log::error!(
+                    "WS_PUBSUB must be memory, postgres or redis, using memory pub/sub: {}",
+                    other
+                );
+                PubSubBackend::Memory
+            }
+        }
+    }
+}
+
+fn redis_url() -> String {
+    std::env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1:6379".to_string())
+}
+
+/// Publishes updates this replica receives from the listener,
+/// and broadcasts updates from every replica to this replica's clients
+pub(crate) fn start(
+    backend: PubSubBackend,
+    outbound: UnboundedReceiver<String>,
+    state: WsState,
+    db_pool: PgPool,
+) {
+    match backend {
+        PubSubBackend::Memory => {}
+        PubSubBackend::Postgres => {
+            tokio::spawn(publish_postgres(outbound, db_pool));
+            spawn_pg_listener(PUBSUB_CHANNEL, state, WsState::broadcast);
+        }
+        PubSubBackend::Redis(url) => {
+            let client = redis::Client::open(url).expect("Invalid REDIS_URL");
+            tokio::spawn(publish_redis(outbound, client.clone()));
+            tokio::spawn(subscribe_redis(client, state));
+        }
+    }
+}
+
+/// Updates wait in `outbound` while an update is retried,
+/// so an outage delays them instead of losing them
+async fn publish_postgres(mut outbound: UnboundedReceiver<String>, db_pool: PgPool) {
+    let mut delay = RECONNECT_BASE_DELAY;
+    while let Some(text) = outbound.recv().await {
+        loop {
+            let result = connect(&db_pool)
+                .map_err(Into::into)
+                .and_then(|mut conn| store::notify(&mut conn, PUBSUB_CHANNEL, &text));
+            match result {
+                Ok(()) => break,
+                Err(e) if e.is_transient() => {
+                    log::error!("Error publishing update to Postgres, retrying: {:?}", e);
+                    back_off(&mut delay).await;
+                }
+                Err(e) => {
+                    // retrying would fail the same way every time
+                    log::error!("Postgres rejected update, dropping it: {:?}", e);
+                    break;
+                }
+            }
+        }
+        delay = RECONNECT_BASE_DELAY;
+    }
+}
+
+/// Like `publish_postgres`, retries each update until Redis takes it
+async fn publish_redis(mut outbound: UnboundedReceiver<String>, client: redis::Client) {
+    let mut conn = None;
+    let mut delay = RECONNECT_BASE_DELAY;
+    while let Some(text) = outbound.recv().await {
+        while let Err(e) = publish_redis_once(&client, &mut conn, &text).await {
+            log::error!("Error publishing update to Redis, reconnecting: {:?}", e);
+            conn = None;
+            back_off(&mut delay).await;
+        }
+        delay = RECONNECT_BASE_DELAY;
+    }
+}
+
+async fn publish_redis_once(
+    client: &redis::Client,
+    conn: &mut Option<MultiplexedConnection>,
+    text: &str,
+) -> redis::RedisResult<()> {
+    let c = match conn {
+        Some(c) => c,
+        None => conn.insert(client.get_multiplexed_async_connection().await?),
+    };
+    let _: i64 = redis::cmd("PUBLISH").arg(PUBSUB_CHANNEL).arg(text).query_async(c).await?;
+    Ok(())
+}
+
+async fn back_off(delay: &mut Duration) {
+    tokio::time::sleep(*delay).await;
+    *delay = (*delay * 2).min(RECONNECT_MAX_DELAY);
+}
+
+async fn subscribe_redis(client: redis::Client, state: WsState) {
+    loop {
+        match client.get_async_pubsub().await {
+            Ok(mut pubsub) => match pubsub.subscribe(PUBSUB_CHANNEL).await {
+                Ok(()) => {
+                    log::info!("Subscribed to Redis channel {}", PUBSUB_CHANNEL);
+                    let mut messages = pubsub.on_message();
+                    while let Some(message) = messages.next().await {
+                        let payload: Vec<u8> = match message.get_payload() {
+                            Ok(payload) => payload,
+                            Err(e) => {
+                                log::error!("Error reading Redis message: {:?}", e);
+                                continue;
+                            }
+                        };
+                        match Broadcast::parse(&payload) {
+                            Ok(broadcast) => state.broadcast(broadcast),
+                            Err(e) => log::error!("Rejecting malformed Redis message: {:?}", e),
+                        }
+                    }
+                    log::warn!("Redis subscription ended, resubscribing");
+                }
+                Err(e) => log::error!("Error subscribing to Redis: {:?}", e),
+            },
+            Err(e) => log::error!("Error connecting to Redis: {:?}", e),
+        }
+        tokio::time::sleep(RECONNECT_DELAY).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::Arc;
+    use tokio::sync::{broadcast::Receiver, mpsc};
+
+    use pump::{db::pool, ListenerMessage, ListenerUpdate};
+
+    use crate::history::Replay;
+
+    fn update(seq: u64) -> Broadcast {
+        let update = ListenerUpdate::GraduatedCoin { id: 1 };
+        Broadcast::new(&ListenerMessage { seq, update, provisional: false }).unwrap()
+    }
+
+    /// Sequence numbers of the messages waiting for a client
+    fn received(rx: &mut Receiver<Arc<Broadcast>>) -> Vec<u64> {
+        let mut seqs = vec![];
+        while let Ok(broadcast) = rx.try_recv() {
+            seqs.push(broadcast.seq);
+        }
+        seqs
+    }
+
+    #[test]
+    fn test_memory_round_trip() {
+        let state = WsState::new(None);
+        let mut rx = state.tx.subscribe();
+        state.publish(update(1));
+        state.publish(update(2));
+        assert_eq!(received(&mut rx), vec![1, 2]);
+    }
+
+    #[test]
+    fn test_duplicates() {
+        let state = WsState::new(None);
+        let mut rx = state.tx.subscribe();
+        // every replica publishes what it hears from the listener
+        for seq in [1, 2, 1, 3, 2, 3] {
+            state.broadcast(update(seq));
+        }
+        assert_eq!(received(&mut rx), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_sequence_restart() {
+        let state = WsState::new(None);
+        let mut rx = state.tx.subscribe();
+        for seq in [100, 101, 5, 6, 5] {
+            state.broadcast(update(seq));
+        }
+        // the new run's updates still get through
+        assert_eq!(received(&mut rx), vec![100, 101, 5, 6]);
+        let history = state.history.lock().unwrap();
+        assert_eq!(history.latest_seq(), Some(6));
+        assert!(matches!(history.since(101), Replay::ResyncRequired));
+        assert!(matches!(history.since(5), Replay::Messages(messages) if messages.len() == 1));
+    }
+
+    /// Publishes an update twice, as two replicas would, and expects it back once
+    async fn assert_round_trip(state: &WsState) {
+        let mut rx = state.tx.subscribe();
+        // give the subscriber time to connect, or the update goes to nobody
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        state.publish(update(1));
+        state.publish(update(1));
+        let timeout = Duration::from_secs(5);
+        let first = tokio::time::timeout(timeout, rx.recv()).await.unwrap().unwrap();
+        assert_eq!(first.seq, 1);
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    #[ignore = "needs Postgres at DATABASE_URL"]
+    async fn test_postgres_round_trip() {
+        let (pubsub_tx, pubsub_rx) = mpsc::unbounded_channel();
+        let state = WsState::new(Some(pubsub_tx));
+        start(PubSubBackend::Postgres, pubsub_rx, state.clone(), pool::establish_pool());
+        assert_round_trip(&state).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "needs redis-server at REDIS_URL"]
+    async fn test_redis_round_trip() {
+        let client = redis::Client::open(redis_url()).unwrap();
+        let (pubsub_tx, pubsub_rx) = mpsc::unbounded_channel();
+        let state = WsState::new(Some(pubsub_tx));
+        tokio::spawn(publish_redis(pubsub_rx, client.clone()));
+        tokio::spawn(subscribe_redis(client, state.clone()));
+        assert_round_trip(&state).await;
+    }
+}
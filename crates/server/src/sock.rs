@@ -1,10 +1,60 @@
 use futures::StreamExt;
 use pump::{get_workspace_root, SOCKET_FILENAME};
 use std::path::Path;
-use tokio::net::UnixListener;
+use tokio::{
+    io::{self, AsyncBufRead, AsyncBufReadExt, AsyncReadExt, BufReader},
+    net::UnixListener,
+};
 use tokio_stream::wrappers::UnixListenerStream;
 
-use crate::state::WsState;
+use crate::{channel::Broadcast, state::WsState};
+
+/// Listener messages are small, so anything bigger than this is garbage
+const MAX_FRAME_LEN: usize = 1 << 20;
+
+enum Frame {
+    Complete,
+    /// skipped through its newline, with its length
+    Oversized(usize),
+    End,
+}
+
+/// Reads the next newline-terminated frame into `frame`,
+/// never buffering more than `MAX_FRAME_LEN + 1` bytes of it
+async fn read_frame<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+    frame: &mut Vec<u8>,
+) -> io::Result<Frame> {
+    frame.clear();
+    let limit = MAX_FRAME_LEN as u64 + 1;
+    if (&mut *reader).take(limit).read_until(b'\n', frame).await? == 0 {
+        return Ok(Frame::End);
+    }
+    if frame.len() <= MAX_FRAME_LEN {
+        return Ok(Frame::Complete);
+    }
+    if frame.last() == Some(&b'\n') {
+        return Ok(Frame::Oversized(frame.len()));
+    }
+    // drop the rest of the frame without holding onto it
+    let mut skipped = frame.len();
+    loop {
+        let buf = reader.fill_buf().await?;
+        if buf.is_empty() {
+            break;
+        }
+        let (len, done) = match buf.iter().position(|b| *b == b'\n') {
+            Some(newline) => (newline + 1, true),
+            None => (buf.len(), false),
+        };
+        reader.consume(len);
+        skipped += len;
+        if done {
+            break;
+        }
+    }
+    Ok(Frame::Oversized(skipped))
+}
 
 pub(crate) fn setup_unix_socket(state: WsState) {
     let workspace = get_workspace_root().unwrap();
@@ -33,30 +83,31 @@ pub(crate) fn setup_unix_socket(state: WsState) {
 
 async fn handle_unix_socket(mut unix_stream: UnixListenerStream, state: WsState) {
     while let Some(Ok(stream)) = unix_stream.next().await {
-        let state_tx = state.tx.clone();
+        let state = state.clone();
 
         tokio::spawn(async move {
-            let mut buf = vec![0; 1024];
-            let stream: tokio::net::UnixStream =
-                tokio::net::UnixStream::from_std(stream.into_std().unwrap()).unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut frame = Vec::new();
 
             loop {
-                match stream.try_read(&mut buf) {
-                    Ok(0) => {
-                        // End of stream
-                        break;
+                match read_frame(&mut reader, &mut frame).await {
+                    Ok(Frame::End) => break,
+                    Ok(Frame::Oversized(len)) => {
+                        log::error!("Dropping {} byte frame from Unix socket", len);
                     }
-                    Ok(n) => {
-                        // Convert bytes to string and broadcast to all WebSocket clients
-                        if let Ok(message) = String::from_utf8(buf[..n].to_vec()) {
-                            log::info!("Received from Unix socket: {}", message);
-                            let _ = state_tx.send(message);
+                    Ok(Frame::Complete) => match Broadcast::parse(&frame) {
+                        Ok(broadcast) => {
+                            log::info!("Received from Unix socket: {}", broadcast.text);
+                            state.publish(broadcast);
                         }
-                    }
-                    Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => {
-                        // Wait for the socket to be readable
-                        tokio::task::yield_now().await;
-                    }
+                        Err(e) => {
+                            log::error!(
+                                "Rejecting malformed frame from Unix socket: {:?}: {}",
+                                e,
+                                String::from_utf8_lossy(&frame)
+                            );
+                        }
+                    },
                     Err(e) => {
                         log::error!("Error reading from Unix socket: {}", e);
                         break;
@@ -4,7 +4,10 @@ use std::{
     collections::HashMap,
     sync::{Arc, Mutex},
 };
-use tokio::sync::broadcast::{self, Sender};
+use tokio::sync::{
+    broadcast::{self, Sender},
+    mpsc::{self, UnboundedSender},
+};
 
 use pump::{
     client::PumpClient,
@@ -12,10 +15,24 @@ use pump::{
     error::PumpError,
 };
 
+use crate::{
+    channel::Broadcast,
+    history::History,
+    limit::ConnectionLimiter,
+    pubsub::{self, PubSubBackend},
+};
+
 #[derive(Clone)]
 pub struct WsState {
-    pub tx: Sender<String>,
+    pub tx: Sender<Arc<Broadcast>>,
     pub clients: Arc<Mutex<HashMap<String, tokio::sync::mpsc::Sender<String>>>>,
+    /// recent messages for clients that reconnect.
+    /// Held while sending to `tx`, so new clients see each message exactly once
+    pub history: Arc<Mutex<History>>,
+    /// sends listener updates to the other replicas, None if this is the only server
+    pub pubsub: Option<UnboundedSender<String>>,
+    /// open connections per client IP
+    pub connections: Arc<ConnectionLimiter>,
 }
 
 #[derive(Clone)]
@@ -26,10 +43,52 @@ pub struct AppState {
     pub ws: WsState,
 }
 
-impl AppState {
-    pub async fn new() -> Result<AppState, PumpError> {
+impl WsState {
+    pub(crate) fn new(pubsub: Option<UnboundedSender<String>>) -> WsState {
         let (tx, _rx) = broadcast::channel(100);
+        WsState {
+            tx,
+            clients: Arc::new(Mutex::new(HashMap::new())),
+            history: Arc::new(Mutex::new(History::from_env())),
+            pubsub,
+            connections: Arc::new(ConnectionLimiter::from_env()),
+        }
+    }
+
+    /// Shares an update from the listener with every replica, including this one
+    pub(crate) fn publish(&self, broadcast: Broadcast) {
+        match &self.pubsub {
+            Some(pubsub) => {
+                if pubsub.send(broadcast.text).is_err() {
+                    log::error!("Pub/sub publisher stopped, dropping update");
+                }
+            }
+            None => self.broadcast(broadcast),
+        }
+    }
+
+    /// Records the message for replay and sends it to every connected client
+    pub(crate) fn broadcast(&self, broadcast: Broadcast) {
+        let mut history = self.history.lock().unwrap();
+        if history.contains(broadcast.seq) {
+            // another replica already published it, e.g. when every replica LISTENs to the listener
+            log::debug!("Skipping duplicate update {}", broadcast.seq);
+            return;
+        }
+        if matches!(history.latest_seq(), Some(latest) if broadcast.seq < latest) {
+            // a new listener run, e.g. after the clock stepped back, so the old messages
+            // can't be replayed in order. Reconnecting clients will have to resync
+            log::warn!("Listener restarted its sequence at {}, clearing history", broadcast.seq);
+            history.clear();
+        }
+        let broadcast = Arc::new(broadcast);
+        history.push(broadcast.clone());
+        let _ = self.tx.send(broadcast);
+    }
+}
 
+impl AppState {
+    pub async fn new() -> Result<AppState, PumpError> {
         let region_provider =
             RegionProviderChain::default_provider().or_else(Region::new("us-east-1"));
         let aws_config = aws_config::from_env().region(region_provider).load().await;
@@ -42,11 +101,20 @@ impl AppState {
         // Establish the database pool.
         let db_pool = pool::establish_pool();
 
+        let backend = PubSubBackend::from_env();
+        log::info!("Sharing websocket updates with {:?} pub/sub", backend);
+        let (pubsub_tx, pubsub_rx) = mpsc::unbounded_channel();
+        let ws = WsState::new(match backend {
+            PubSubBackend::Memory => None,
+            _ => Some(pubsub_tx),
+        });
+        pubsub::start(backend, pubsub_rx, ws.clone(), db_pool.clone());
+
         Ok(AppState {
             s3_client: shared_s3_client,
             db_pool,
             pump_client: Arc::new(pump_client),
-            ws: WsState { tx: tx.clone(), clients: Arc::new(Mutex::new(HashMap::new())) },
+            ws,
         })
     }
 
@@ -1,41 +1,112 @@
 use axum::{
     extract::{
         ws::{Message, WebSocket},
-        State, WebSocketUpgrade,
+        ConnectInfo, Query, State, WebSocketUpgrade,
     },
-    response::IntoResponse,
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
 };
 use futures::{SinkExt, StreamExt};
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+use tokio::sync::broadcast::error::RecvError;
 use uuid::Uuid;
 
-use crate::state::{AppState, WsState};
+use pump::error::PumpError;
+
+use crate::{
+    channel::{ClientMessage, ServerMessage, Subscriptions},
+    history::Replay,
+    limit::{self, ConnectionGuard},
+    state::{AppState, WsState},
+};
 
+/// Handler for GET /ws?since=<seq>&channels=<channels>, where `since` is the last sequence number
+/// the client saw and `channels` what it was subscribed to, see `Subscriptions::from_query`.
+/// Rejects clients that already have the maximum number of sockets open
 pub(crate) async fn ws_handler(
     ws: WebSocketUpgrade,
     State(state): State<AppState>,
-) -> impl IntoResponse {
-    ws.on_upgrade(|socket| handle_socket(socket, state.ws))
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Query(params): Query<HashMap<String, String>>,
+) -> Response {
+    let ip = limit::client_ip(&headers, peer);
+    let Some(guard) = state.ws.connections.acquire(ip) else {
+        log::debug!("Rejecting websocket from {}: too many connections", ip);
+        return (StatusCode::TOO_MANY_REQUESTS, "Too many websocket connections").into_response();
+    };
+    let subscriptions = match Subscriptions::from_query(params.get("channels")) {
+        Ok(subscriptions) => subscriptions,
+        Err(e) => return PumpError::InvalidParam(e).into_response(),
+    };
+    let since = params.get("since").and_then(|since| since.parse::<u64>().ok());
+    ws.on_upgrade(move |socket| handle_socket(socket, state.ws, since, subscriptions, guard))
 }
 
-async fn handle_socket(socket: WebSocket, state: WsState) {
+async fn handle_socket(
+    socket: WebSocket,
+    state: WsState,
+    since: Option<u64>,
+    subscriptions: Subscriptions,
+    // released when the socket closes
+    _guard: ConnectionGuard,
+) {
     let (mut sender, mut receiver) = socket.split();
 
     let client_id = Uuid::new_v4().to_string();
 
-    let (tx, mut _rx) = tokio::sync::mpsc::channel(100);
+    // replies to this client's own messages
+    let (tx, mut rx) = tokio::sync::mpsc::channel(100);
 
     {
         let mut clients = state.clients.lock().unwrap();
-        clients.insert(client_id.clone(), tx);
+        clients.insert(client_id.clone(), tx.clone());
     }
 
-    let mut broadcast_rx = state.tx.subscribe();
+    let subscriptions = Arc::new(Mutex::new(subscriptions));
+
+    // subscribe while holding the history, so each message is either replayed or received live
+    let (mut broadcast_rx, replay) = {
+        let history = state.history.lock().unwrap();
+        let replay = since.map(|since| match history.since(since) {
+            Replay::Messages(messages) => Ok(messages),
+            Replay::ResyncRequired => Err(history.latest_seq()),
+        });
+        (state.tx.subscribe(), replay)
+    };
 
     // Create a simple signal channel for clean shutdown
     let (shutdown_tx, mut shutdown_rx) = tokio::sync::oneshot::channel();
 
     let client_id_clone = client_id.clone();
+    let forwarding_subscriptions = subscriptions.clone();
+    let history = state.history.clone();
     let forwarding_task = tokio::spawn(async move {
+        match replay {
+            Some(Ok(messages)) => {
+                log::debug!("Replaying {} messages to client {}", messages.len(), client_id_clone);
+                for msg in messages {
+                    if !forwarding_subscriptions.lock().unwrap().wants(&msg) {
+                        continue;
+                    }
+                    if sender.send(Message::Text(msg.text.clone())).await.is_err() {
+                        return;
+                    }
+                }
+            }
+            Some(Err(latest_seq)) => {
+                let resync = serde_json::to_string(&ServerMessage::ResyncRequired { latest_seq });
+                if sender.send(Message::Text(resync.unwrap())).await.is_err() {
+                    return;
+                }
+            }
+            None => {}
+        }
+
         loop {
             tokio::select! {
                 // Exit loop when shutdown signal received
@@ -44,14 +115,31 @@ async fn handle_socket(socket: WebSocket, state: WsState) {
                     break;
                 },
                 msg = broadcast_rx.recv() => {
-                    if let Ok(msg) = msg {
-                        log::debug!("Forwarding message to client {}: {}", client_id_clone, msg);
-                        if let Err(e) = sender.send(Message::Text(msg)).await {
-                            log::error!("Failed to send message to client {}: {:?}", client_id_clone, e);
-                            break;
+                    let text = match msg {
+                        Ok(msg) => {
+                            if !forwarding_subscriptions.lock().unwrap().wants(&msg) {
+                                continue;
+                            }
+                            log::debug!("Forwarding message to client {}: {}", client_id_clone, msg.text);
+                            msg.text.clone()
+                        }
+                        Err(RecvError::Lagged(skipped)) => {
+                            // the client keeps its socket, but has to refetch what it missed
+                            log::warn!("Client {} lagged behind by {} messages", client_id_clone, skipped);
+                            let latest_seq = history.lock().unwrap().latest_seq();
+                            serde_json::to_string(&ServerMessage::ResyncRequired { latest_seq }).unwrap()
                         }
-                    } else {
                         // Broadcast channel closed
+                        Err(RecvError::Closed) => break,
+                    };
+                    if let Err(e) = sender.send(Message::Text(text)).await {
+                        log::error!("Failed to send message to client {}: {:?}", client_id_clone, e);
+                        break;
+                    }
+                }
+                Some(reply) = rx.recv() => {
+                    if let Err(e) = sender.send(Message::Text(reply)).await {
+                        log::error!("Failed to send message to client {}: {:?}", client_id_clone, e);
                         break;
                     }
                 }
@@ -64,8 +152,18 @@ async fn handle_socket(socket: WebSocket, state: WsState) {
         while let Some(Ok(msg)) = receiver.next().await {
             match msg {
                 Message::Text(text) => {
-                    // NOTE: handle client-to-server messages here if needed
                     log::debug!("Received message from client {}: {}", client_id_clone, text);
+                    let reply = match serde_json::from_str::<ClientMessage>(&text) {
+                        Ok(message) => {
+                            let channels = subscriptions.lock().unwrap().handle(message);
+                            ServerMessage::Subscribed(channels)
+                        }
+                        Err(e) => ServerMessage::Error(format!("Invalid message: {}", e)),
+                    };
+                    let reply = serde_json::to_string(&reply).unwrap();
+                    if tx.send(reply).await.is_err() {
+                        break;
+                    }
                 }
                 Message::Close(_) => {
                     log::debug!("Client {} sent close frame", client_id_clone);
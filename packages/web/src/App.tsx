@@ -1,4 +1,4 @@
-import React, { useEffect, useRef } from 'react'
+import React, { useEffect, useRef, useState } from 'react'
 import { PropsWithChildren } from 'react'
 import { useDispatch } from 'react-redux'
 import { BrowserRouter, Route, Routes, useNavigate } from 'react-router-dom'
@@ -14,6 +14,7 @@ import CoinDetail from '@/components/coin/coin-detail'
 import CoinForm from '@/components/create/coin-form'
 import HallOfFame from '@/components/hall-of-fame/page'
 import { CHAIN_ID } from '@/hooks/useContract'
+import { WebSocketContext } from '@/hooks/useWebSocket'
 import Home from '@/pages/Home'
 import NotFound from '@/pages/NotFound'
 import { AppDispatch } from '@/store/store'
@@ -65,6 +66,7 @@ const AppRoutes: React.FC = () => {
   const dispatch = useDispatch<AppDispatch>()
   const navigate = useNavigate()
   const wsRef = useRef<WebSocketService | null>(null)
+  const [ws, setWs] = useState<WebSocketService | null>(null)
 
   useEffect(() => {
     // Fetch all coins when component mounts
@@ -76,23 +78,27 @@ const AppRoutes: React.FC = () => {
       const websocketService = new WebSocketService(WEBSOCKET_URL)
       websocketService.init(dispatch, navigate)
       wsRef.current = websocketService
+      setWs(websocketService)
     }
     return () => {
       if (wsRef.current) {
         wsRef.current.disconnect()
         wsRef.current = null
+        setWs(null)
       }
     }
   }, [dispatch, navigate])
 
   return (
-    <Routes>
-      <Route path="/" element={<Home />} />
-      <Route path="/create" element={<CoinForm />} />
-      <Route path="/coins/:coinId" element={<CoinDetail />} />
-      <Route path="/hall-of-fame" element={<HallOfFame />} />
-      <Route path="*" element={<NotFound />} />
-    </Routes>
+    <WebSocketContext.Provider value={ws}>
+      <Routes>
+        <Route path="/" element={<Home />} />
+        <Route path="/create" element={<CoinForm />} />
+        <Route path="/coins/:coinId" element={<CoinDetail />} />
+        <Route path="/hall-of-fame" element={<HallOfFame />} />
+        <Route path="*" element={<NotFound />} />
+      </Routes>
+    </WebSocketContext.Provider>
   )
 }
 
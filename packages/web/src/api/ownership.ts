@@ -0,0 +1,79 @@
+import { type Hex, keccak256 } from 'viem'
+
+// the server rejects signatures that live longer than 15 minutes
+const SIGNATURE_TTL_SECONDS = 5 * 60
+
+export type CoinMetadata = {
+  description: string | null
+  imageUrl: string | null
+  website: string | null
+  telegram: string | null
+  twitter: string | null
+}
+
+export type CoinWrite =
+  | { action: 'metadata'; metadata: CoinMetadata }
+  | { action: 'upload'; image: Uint8Array }
+
+type OwnershipMessageParams = {
+  chainId: number
+  coinId: number
+  write: CoinWrite
+  nonce: string
+  expiresAt: number
+}
+
+// Must match `ownership::message` on the server
+export const ownershipMessage = ({
+  chainId,
+  coinId,
+  write,
+  nonce,
+  expiresAt,
+}: OwnershipMessageParams): string => {
+  const lines = [`Update coin ${coinId} on Moonhatch`, `Chain ID: ${chainId}`]
+  if (write.action === 'metadata') {
+    const { metadata } = write
+    lines.push(
+      'Action: metadata',
+      `Description: ${metadata.description ?? ''}`,
+      `Image URL: ${metadata.imageUrl ?? ''}`,
+      `Website: ${metadata.website ?? ''}`,
+      `Telegram: ${metadata.telegram ?? ''}`,
+      `Twitter: ${metadata.twitter ?? ''}`
+    )
+  } else {
+    lines.push('Action: upload', `Image: ${keccak256(write.image)}`)
+  }
+  lines.push(`Nonce: ${nonce}`, `Expires At: ${expiresAt}`)
+  return lines.join('\n')
+}
+
+const randomNonce = (): string =>
+  Array.from(crypto.getRandomValues(new Uint8Array(16)))
+    .map((b) => b.toString(16).padStart(2, '0'))
+    .join('')
+
+// Signs a write to a coin with its creator's wallet,
+// returning the headers that prove ownership to the server
+export const signCoinWrite = async ({
+  chainId,
+  coinId,
+  write,
+  signMessage,
+}: {
+  chainId: number
+  coinId: number
+  write: CoinWrite
+  signMessage: (message: string) => Promise<Hex>
+}): Promise<Record<string, string>> => {
+  const nonce = randomNonce()
+  const expiresAt = Math.floor(Date.now() / 1000) + SIGNATURE_TTL_SECONDS
+  const message = ownershipMessage({ chainId, coinId, write, nonce, expiresAt })
+  const signature = await signMessage(message)
+  return {
+    'X-Signature': signature,
+    'X-Signature-Nonce': nonce,
+    'X-Signature-Expires': expiresAt.toString(),
+  }
+}
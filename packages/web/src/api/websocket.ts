@@ -1,12 +1,13 @@
 import { NavigateFunction } from 'react-router-dom'
-import { Dispatch } from 'redux'
 import { parseEther } from 'viem'
 
+import { fetchCoinsAction } from '@/api/dispatch'
 import { sendWsToast } from '@/components/WsToast'
 import { selectCoinById, updateCoin } from '@/store/slice'
-import { RootState, store } from '@/store/store'
+import { AppDispatch, RootState, store } from '@/store/store'
 import { Coin } from '@/types/coin'
-import type { CoinUpdate } from '@/types/update'
+import type { CoinUpdate, WsChannel, WsMessage } from '@/types/update'
+import { isCoinUpdate } from '@/types/update'
 
 type SelectorFactory = (
   coinId: string
@@ -26,31 +27,57 @@ const createCachedSelectorGetter = (
   }
 }
 
+export type MessageListener = (message: WsMessage) => void
+
+// the form /ws?channels= takes
+const channelParam = (channel: WsChannel): string => {
+  switch (channel.type) {
+    case 'coins':
+      return 'coins'
+    case 'coin':
+      return `coin:${channel.id}`
+    case 'pool':
+    case 'trader':
+      return `${channel.type}:${channel.address.toLowerCase()}`
+  }
+}
+
 // Create the cached getter
 const getCoinSelector = createCachedSelectorGetter(selectCoinById)
 
 class WebSocketService {
   private socket: WebSocket | null = null
-  private dispatch: Dispatch | null = null
+  private dispatch: AppDispatch | null = null
   private navigate: NavigateFunction | null = null
   private url: string
   private reconnectAttempts = 0
   private maxReconnectAttempts = 5
   private reconnectTimeout = 3000 // 3 seconds
   private reconnectOnClose = true
+  // last sequence number we received, so the server can replay what we missed
+  private lastSeq: number | null = null
+  // components that react to trades, candles and reorgs
+  private listeners = new Set<MessageListener>()
+  // what we're subscribed to, by channelParam, so a reconnect resumes the same channels
+  private channels = new Map<string, WsChannel>([['coins', { type: 'coins' }]])
 
   constructor(url: string) {
     this.url = url
   }
 
   // Initialize with Redux dispatch
-  init(dispatch: Dispatch, navigate: NavigateFunction) {
+  init(dispatch: AppDispatch, navigate: NavigateFunction) {
     this.dispatch = dispatch
     this.navigate = navigate
     this.connect()
   }
 
   onUpdate(update: CoinUpdate) {
+    if (update.provisional || update.type === 'correctedCoin') {
+      // only toast once the update is confirmed, and not for reorg corrections
+      return
+    }
+
     const onClick = () => {
       if (this.navigate) {
         this.navigate(`/coins/${update.data.id}`)
@@ -101,16 +128,54 @@ class WebSocketService {
     }
 
     try {
-      this.socket = new WebSocket(this.url)
+      // the server filters the replay by these, so they have to be known before it starts
+      const params = new URLSearchParams({
+        channels: [...this.channels.keys()].join(','),
+      })
+      if (this.lastSeq !== null) {
+        params.set('since', this.lastSeq.toString())
+      }
+      this.socket = new WebSocket(`${this.url}?${params}`)
 
       this.socket.onopen = () => {
         console.log('WebSocket connection established')
         this.reconnectAttempts = 0
+        // covers channels subscribed to while we were connecting
+        this.send({
+          action: 'subscribe',
+          channels: [...this.channels.values()],
+        })
       }
 
       this.socket.onmessage = (event) => {
         try {
-          const update: CoinUpdate = JSON.parse(event.data)
+          const message: WsMessage = JSON.parse(event.data)
+          if ('seq' in message && message.seq !== undefined) {
+            this.lastSeq = message.seq
+          }
+          if (message.type === 'resyncRequired') {
+            // we missed too much to replay, so start over from the next message
+            console.warn('Missed websocket updates, refresh to resync')
+            this.lastSeq = message.data.latestSeq
+            return
+          }
+          this.listeners.forEach((listener) => listener(message))
+          if (message.type === 'reorg') {
+            // corrections for coins the reorg touched follow as correctedCoin messages,
+            // but coins created in the orphaned blocks only go away with a refetch
+            console.warn(
+              `Chain reorg: ${message.data.depth} blocks after ${message.data.forkBlock} replaced`
+            )
+            this.dispatch?.(fetchCoinsAction()).catch((error) =>
+              console.error('Error refetching coins after reorg:', error)
+            )
+            return
+          }
+          if (!isCoinUpdate(message)) {
+            // trades, candles and subscription replies don't change the coin entity
+            return
+          }
+          const update: CoinUpdate = message
           if (update.type === 'graduatedCoin') {
             update.data.graduated = true
           }
@@ -170,6 +235,34 @@ class WebSocketService {
     }
   }
 
+  // Calls `listener` with every message, returning a function that removes it
+  addListener(listener: MessageListener): () => void {
+    this.listeners.add(listener)
+    return () => {
+      this.listeners.delete(listener)
+    }
+  }
+
+  // Receive updates on these channels, in addition to coin updates.
+  // If we're not connected, they're sent when we are
+  subscribe(channels: WsChannel[]) {
+    channels.forEach((channel) =>
+      this.channels.set(channelParam(channel), channel)
+    )
+    this.send({ action: 'subscribe', channels })
+  }
+
+  unsubscribe(channels: WsChannel[]) {
+    channels.forEach((channel) => this.channels.delete(channelParam(channel)))
+    this.send({ action: 'unsubscribe', channels })
+  }
+
+  private send(message: object) {
+    if (this.isConnected()) {
+      this.socket!.send(JSON.stringify(message))
+    }
+  }
+
   // Check if the connection is active
   isConnected(): boolean {
     return this.socket !== null && this.socket.readyState === WebSocket.OPEN
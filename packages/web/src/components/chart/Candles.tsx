@@ -2,12 +2,14 @@ import {
   CandlestickSeries,
   ColorType,
   ISeriesApi,
+  UTCTimestamp,
   createChart,
 } from 'lightweight-charts'
 import React, { useEffect, useRef } from 'react'
 import type { Hex } from 'viem'
 
 import { useTimeseries } from '@/hooks/useTimeseries'
+import { useWebSocket } from '@/hooks/useWebSocket'
 
 type ChartColors = {
   backgroundColor?: string
@@ -23,6 +25,7 @@ const REFRESH_CHART_MS = 5000 // Refresh interval in milliseconds
 
 export const ChartComponent: React.FC<ChartProps> = ({ pool, colors = {} }) => {
   const { fetchTimeseries } = useTimeseries()
+  const ws = useWebSocket()
   const { backgroundColor = '#161b33', textColor = '#f1dac4' } = colors
 
   const chartContainerRef = useRef<HTMLDivElement | null>(null)
@@ -110,8 +113,7 @@ export const ChartComponent: React.FC<ChartProps> = ({ pool, colors = {} }) => {
       })
       .catch((err) => console.error(err))
 
-    // Set up an interval to refresh chart data.
-    const refreshInterval = setInterval(() => {
+    const refresh = () => {
       fetchTimeseries({ pool })
         .then((ts) => {
           if (!isMounted) return
@@ -121,7 +123,37 @@ export const ChartComponent: React.FC<ChartProps> = ({ pool, colors = {} }) => {
           }
         })
         .catch((err) => console.error(err))
-    }, REFRESH_CHART_MS)
+    }
+
+    // Set up an interval to refresh chart data.
+    const refreshInterval = setInterval(refresh, REFRESH_CHART_MS)
+
+    // stream this pool's candles as blocks come in
+    const channel = { type: 'pool', address: pool } as const
+    ws?.subscribe([channel])
+    const removeListener = ws?.addListener((message) => {
+      // candles from orphaned blocks are deleted, so don't wait for the next refresh
+      if (message.type === 'reorg') refresh()
+      if (
+        message.type === 'candle' &&
+        message.data.pool.toLowerCase() === pool.toLowerCase()
+      ) {
+        const { time, open, high, low, close } = message.data
+        try {
+          // a provisional candle is replaced by its confirmed one, which has the same time
+          seriesRef.current?.update({
+            time: time as UTCTimestamp,
+            open,
+            high,
+            low,
+            close,
+          })
+        } catch (err) {
+          // older than the last bar, e.g. during a replay; the next refresh has it
+          console.error(err)
+        }
+      }
+    })
 
     // Optionally handle container resizing.
     const handleResize = () => {
@@ -134,11 +166,13 @@ export const ChartComponent: React.FC<ChartProps> = ({ pool, colors = {} }) => {
     return () => {
       isMounted = false
       clearInterval(refreshInterval)
+      removeListener?.()
+      ws?.unsubscribe([channel])
       window.removeEventListener('resize', handleResize)
       seriesRef.current = null
       chart.remove()
     }
-  }, [pool, backgroundColor, textColor, fetchTimeseries])
+  }, [pool, backgroundColor, textColor, fetchTimeseries, ws])
 
   return (
     <>
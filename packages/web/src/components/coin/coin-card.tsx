@@ -4,15 +4,13 @@ import { formatEther } from 'viem'
 
 import CoinSocials from '@/components/coin/coin-social'
 import { TokenBalance } from '@/components/coin/token-balance'
+import { usePumpConfig } from '@/hooks/usePumpConfig'
 import { Coin } from '@/types/coin'
 import { formatRelativeTime } from '@/util'
 import LockIcon from '@mui/icons-material/Lock'
 import SchoolIcon from '@mui/icons-material/School'
 import { Box, CircularProgress, Tooltip, Typography } from '@mui/material'
 
-// In the contract, 1 ETH (1e18 wei) is needed to graduate
-const WEI_GRADUATION = 1000000000000000000n
-
 interface CoinCardProps {
   coin: Coin
 }
@@ -22,6 +20,7 @@ export const FALLBACK_IMAGE_URL =
 
 const CoinCard: React.FC<CoinCardProps> = ({ coin }) => {
   const navigate = useNavigate()
+  const { weiGraduation } = usePumpConfig()
 
   // State for scrambled text elements (only name & ticker)
   const [scrambledName, setScrambledName] = useState(coin.name.toUpperCase())
@@ -32,10 +31,10 @@ const CoinCard: React.FC<CoinCardProps> = ({ coin }) => {
   // Calculate graduation progress for non-graduated coins
   const weiIn = !coin.graduated ? BigInt(coin.weiIn || '0') : 0n
   const graduationPercentage = !coin.graduated
-    ? Math.min(Number((weiIn * 100n) / WEI_GRADUATION), 100)
+    ? Math.min(Number((weiIn * 100n) / weiGraduation), 100)
     : 0
   const tooltipText = !coin.graduated
-    ? `${graduationPercentage}% to graduation (${formatEther(weiIn)} / ${formatEther(WEI_GRADUATION)} ETH)`
+    ? `${graduationPercentage}% to graduation (${formatEther(weiIn)} / ${formatEther(weiGraduation)} ETH)`
     : ''
 
   useEffect(() => {
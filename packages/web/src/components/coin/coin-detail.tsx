@@ -8,6 +8,7 @@ import { Candles } from '@/components/chart/Candles'
 import CoinInfoDetails from '@/components/coin-detail/coin-info-details'
 import TradeSection from '@/components/coin-detail/trade-section'
 import CoinSocials from '@/components/coin/coin-social'
+import { usePumpConfig } from '@/hooks/usePumpConfig'
 import { useToastNotifications } from '@/hooks/useToastNotifications'
 import NotFound from '@/pages/NotFound'
 import { selectCoinById } from '@/store/slice'
@@ -16,13 +17,12 @@ import type { Coin } from '@/types/coin'
 import LockIcon from '@mui/icons-material/Lock'
 import { Box, CircularProgress, Typography } from '@mui/material'
 
-const WEI_GRADUATION = 1000000000000000000n
-
 const CoinDetailGraph: React.FC<{ coin: Coin }> = ({ coin }) => {
+  const { weiGraduation } = usePumpConfig()
   const weiIn = !coin.graduated ? BigInt(coin.weiIn || '0') : 0n
 
   const percentage = !coin.graduated
-    ? Math.min(Number((weiIn * 100n) / WEI_GRADUATION), 100)
+    ? Math.min(Number((weiIn * 100n) / weiGraduation), 100)
     : 0
 
   return (
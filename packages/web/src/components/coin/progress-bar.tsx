@@ -1,6 +1,7 @@
 import React from 'react'
 import { formatEther } from 'viem'
 
+import { usePumpConfig } from '@/hooks/usePumpConfig'
 import { Coin } from '@/types/coin'
 import { Box, Tooltip, Typography } from '@mui/material'
 
@@ -11,18 +12,18 @@ type GraduationProgressProps = {
 export const GraduationProgress: React.FC<GraduationProgressProps> = ({
   coin,
 }) => {
-  // In the contract, 1 ETH (1e18 wei) is needed to graduate
-  const WEI_GRADUATION = 1000000000000000000n
+  // read from the deployed contract, 1 ETH at launch
+  const { weiGraduation } = usePumpConfig()
 
   // Parse the weiIn value from string to bigint
   const weiIn = BigInt(coin.weiIn || '0')
 
   // Calculate percentage (capped at 100%)
-  const percentage = Math.min(Number((weiIn * 100n) / WEI_GRADUATION), 100)
+  const percentage = Math.min(Number((weiIn * 100n) / weiGraduation), 100)
 
   // Format values for display
   const formattedWeiIn = formatEther(weiIn)
-  const formattedTarget = formatEther(WEI_GRADUATION)
+  const formattedTarget = formatEther(weiGraduation)
 
   return (
     <Box sx={{ width: '100%', mt: 1, mb: 1 }}>
@@ -82,14 +82,18 @@ const CoinForm: React.FC = () => {
       ? await uploadImage(coinId, formData.image)
       : null
 
+    // signing the metadata can fail, e.g. if the user rejects the signature
     const backendResponse = await postCreatedCoin({
       coinId,
       formData,
       receipt,
       imageUrl,
+    }).catch((error) => {
+      console.error('Failed to sign coin metadata', error)
+      return null
     })
 
-    if (!backendResponse.ok) {
+    if (!backendResponse?.ok) {
       notifyError('Failed to broadcast transaction')
       console.error('Failed to save coin to backend')
       return
@@ -1,9 +1,11 @@
 import { useCallback, useEffect, useState } from 'react'
 import { useDispatch, useSelector } from 'react-redux'
+import { useShieldedWallet } from 'seismic-react'
 import { TransactionReceipt } from 'viem'
 
 import { BASE_API_URL } from '@/api'
 import { fetchCoinByIdAction } from '@/api/dispatch'
+import { type CoinWrite, signCoinWrite } from '@/api/ownership'
 import { selectAllCoins } from '@/store/slice'
 import { AppDispatch } from '@/store/store'
 import type { Coin, CoinFormData } from '@/types/coin'
@@ -14,6 +16,7 @@ export function useFetchCoin() {
   const [error, setError] = useState<Error | null>(null)
 
   const dispatch = useDispatch<AppDispatch>()
+  const { walletClient } = useShieldedWallet()
 
   // Safely get coins from the store - guarantees an array (even empty)
   const allCoins = useSelector(selectAllCoins)
@@ -47,8 +50,26 @@ export function useFetchCoin() {
     [dispatch]
   )
 
+  // the server only accepts writes signed by the coin's creator
+  const signWrite = useCallback(
+    async (coinId: number, write: CoinWrite) => {
+      if (!walletClient?.account || !walletClient.chain) {
+        throw new Error('Connect the wallet that created the coin')
+      }
+      const account = walletClient.account
+      return signCoinWrite({
+        chainId: walletClient.chain.id,
+        coinId,
+        write,
+        signMessage: (message) =>
+          walletClient.signMessage({ account, message }),
+      })
+    },
+    [walletClient]
+  )
+
   const postCreatedCoin = useCallback(
-    ({
+    async ({
       coinId,
       formData,
       imageUrl,
@@ -59,10 +80,22 @@ export function useFetchCoin() {
       imageUrl: string | null
       receipt: TransactionReceipt
     }) => {
+      const metadata = {
+        description: formData.description || null,
+        imageUrl,
+        twitter: formData.twitter || null,
+        website: formData.website || null,
+        telegram: formData.telegram || null,
+      }
+      const signatureHeaders = await signWrite(coinId, {
+        action: 'metadata',
+        metadata,
+      })
       return fetch(`${BASE_API_URL}/coins/create`, {
         method: 'POST',
         headers: {
           'Content-Type': 'application/json',
+          ...signatureHeaders,
         },
         body: JSON.stringify({
           id: coinId,
@@ -74,15 +107,11 @@ export function useFetchCoin() {
           creator: receipt.from,
           graduated: false,
           verified: false,
-          description: formData.description || null,
-          imageUrl,
-          twitter: formData.twitter || null,
-          website: formData.website || null,
-          telegram: formData.telegram || null,
+          ...metadata,
         }),
       })
     },
-    []
+    [signWrite]
   )
 
   const uploadImage = useCallback(
@@ -93,8 +122,13 @@ export function useFetchCoin() {
       const body = new FormData()
       body.append('file', image)
       try {
+        const signatureHeaders = await signWrite(coinId, {
+          action: 'upload',
+          image: new Uint8Array(await image.arrayBuffer()),
+        })
         const response = await fetch(`${BASE_API_URL}/coin/${coinId}/upload`, {
           method: 'POST',
+          headers: signatureHeaders,
           body,
         })
 
@@ -110,7 +144,7 @@ export function useFetchCoin() {
         return null
       }
     },
-    []
+    [signWrite]
   )
 
   const verifyCoin = useCallback((coinId: number): Promise<Response> => {
@@ -0,0 +1,50 @@
+import { useEffect, useState } from 'react'
+
+import { BASE_API_URL } from '@/api'
+
+export type PumpConfig = {
+  chainId: number
+  pump: string
+  // wei a coin must raise before it graduates
+  weiGraduation: bigint
+  feeBps: number
+}
+
+// PumpRand's value at launch, used until the server responds
+const DEFAULT_WEI_GRADUATION = 1000000000000000000n
+
+// the config only changes with a new deployment, so fetch it once per page load
+let configPromise: Promise<PumpConfig> | null = null
+
+export const fetchPumpConfig = (): Promise<PumpConfig> => {
+  if (!configPromise) {
+    configPromise = fetch(`${BASE_API_URL}/config`)
+      .then(async (r) => {
+        if (!r.ok) {
+          throw new Error(`Failed to fetch pump config: ${await r.text()}`)
+        }
+        return r.json()
+      })
+      .then((data) => ({ ...data, weiGraduation: BigInt(data.weiGraduation) }))
+      .catch((error) => {
+        configPromise = null
+        throw error
+      })
+  }
+  return configPromise
+}
+
+export const usePumpConfig = () => {
+  const [config, setConfig] = useState<PumpConfig | null>(null)
+
+  useEffect(() => {
+    fetchPumpConfig()
+      .then(setConfig)
+      .catch((error) => console.error(error))
+  }, [])
+
+  return {
+    config,
+    weiGraduation: config?.weiGraduation ?? DEFAULT_WEI_GRADUATION,
+  }
+}
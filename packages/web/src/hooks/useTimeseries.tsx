@@ -3,8 +3,12 @@ import type { Hex } from 'viem'
 
 import { BASE_API_URL } from '@/api'
 
+export type CandleInterval = '1m' | '5m' | '1h' | '1d'
+
 type FetchTimeseriesParams = {
   pool: Hex
+  // omit for one candle per block
+  interval?: CandleInterval
   maxTs?: number
   minTs?: number
   limit?: number
@@ -16,6 +20,9 @@ export type TimeseriesDatapoint = {
   high: number
   low: number
   close: number
+  volumeBase: number
+  volumeQuote: number
+  tradeCount: number
 }
 
 const encodeGetParams = (p: { [key: string]: string | number | boolean }) =>
@@ -0,0 +1,8 @@
+import { createContext, useContext } from 'react'
+
+import WebSocketService from '@/api/websocket'
+
+// null until the app connects
+export const WebSocketContext = createContext<WebSocketService | null>(null)
+
+export const useWebSocket = () => useContext(WebSocketContext)
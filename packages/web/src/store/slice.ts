@@ -55,6 +55,10 @@ const coinsSlice = createSlice({
       } else if (update.type === 'verifiedCoin') {
         state.entities[coinId] = update.data as Coin
       }
+      if (update.type === 'correctedCoin') {
+        // the server's copy replaces whatever the orphaned blocks told us
+        state.entities[coinId] = update.data
+      }
     },
   },
 })
@@ -31,9 +31,107 @@ type DeployedToDex = {
   data: Pick<Coin, 'id' | 'deployedPool'>
 }
 
-export type CoinUpdate =
+// the coin's state after the listener rolled back a reorg
+type CorrectedCoin = {
+  type: 'correctedCoin'
+  data: Coin
+}
+
+// blocks after `forkBlock` were replaced, so trades and candles from them may be gone
+export type Reorg = {
+  type: 'reorg'
+  data: { forkBlock: number; depth: number }
+  seq?: number
+}
+
+export type Trade = {
+  pool: string
+  coinId: number | null
+  side: 'buy' | 'sell'
+  amountToken: number
+  amountEth: number
+  price: number
+  trader: string
+  tx: string
+  time: number
+}
+
+export type TradeUpdate = {
+  type: 'trade'
+  data: Trade
+  seq?: number
+}
+
+export type WsChannel =
+  | { type: 'coins' }
+  | { type: 'coin'; id: number }
+  | { type: 'pool'; address: string }
+  | { type: 'trader'; address: string }
+
+type Subscribed = {
+  type: 'subscribed'
+  data: WsChannel[]
+}
+
+type WsError = {
+  type: 'error'
+  data: string
+}
+
+type ResyncRequired = {
+  type: 'resyncRequired'
+  data: { latestSeq: number | null }
+}
+
+export type CandleUpdate = {
+  type: 'candle'
+  data: {
+    pool: string
+    blockNumber: number
+    time: number
+    open: number
+    high: number
+    low: number
+    close: number
+    volumeBase: number
+    volumeQuote: number
+    tradeCount: number
+  }
+  // set for the in-progress block, which is replaced once it's confirmed
+  provisional?: boolean
+  seq?: number
+}
+
+export type CoinUpdate = (
   | AddCoin
   | VerifiedCoin
   | WeiInUpdated
   | GraduatedCoin
   | DeployedToDex
+  | CorrectedCoin
+) & {
+  // set until the update's block is confirmed
+  provisional?: boolean
+  seq?: number
+}
+
+export type WsMessage =
+  | CoinUpdate
+  | TradeUpdate
+  | CandleUpdate
+  | Reorg
+  | Subscribed
+  | WsError
+  | ResyncRequired
+
+const COIN_UPDATE_TYPES: string[] = [
+  'coin',
+  'verifiedCoin',
+  'weiInUpdated',
+  'graduatedCoin',
+  'deployedToDex',
+  'correctedCoin',
+]
+
+export const isCoinUpdate = (message: WsMessage): message is CoinUpdate =>
+  COIN_UPDATE_TYPES.includes(message.type)